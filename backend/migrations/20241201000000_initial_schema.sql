@@ -0,0 +1,41 @@
+-- diaries recorded by users; `local_date` is the user's calendar day of the recording
+CREATE TABLE IF NOT EXISTS diary (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    local_date DATE NOT NULL DEFAULT CURRENT_DATE,
+    user_id UUID NOT NULL,
+    audio_link TEXT,
+    summary TEXT,
+    transcription TEXT,
+    emotion TEXT,
+    is_private BOOLEAN NOT NULL DEFAULT false
+);
+
+CREATE INDEX IF NOT EXISTS diary_user_id_local_date_idx ON diary (user_id, local_date);
+CREATE INDEX IF NOT EXISTS diary_user_id_created_at_idx ON diary (user_id, created_at);
+
+-- catalog of decorations that can be placed in a room
+CREATE TABLE IF NOT EXISTS deco (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    name TEXT NOT NULL UNIQUE,
+    asset_link TEXT NOT NULL,
+    category TEXT,
+    is_valid BOOLEAN NOT NULL DEFAULT true,
+    display_name TEXT
+);
+
+-- decorations a user earned with a diary, and where they are placed in the room
+CREATE TABLE IF NOT EXISTS user_deco (
+    user_id UUID NOT NULL,
+    diary_id BIGINT NOT NULL REFERENCES diary (id) ON DELETE CASCADE,
+    deco_id BIGINT NOT NULL REFERENCES deco (id),
+    is_placed BOOLEAN NOT NULL DEFAULT false,
+    coordinates JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, diary_id, deco_id)
+);
+
+CREATE INDEX IF NOT EXISTS user_deco_diary_id_idx ON user_deco (diary_id);
+CREATE INDEX IF NOT EXISTS user_deco_deco_id_idx ON user_deco (deco_id);
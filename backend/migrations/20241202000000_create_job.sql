@@ -0,0 +1,20 @@
+CREATE TYPE job_status AS ENUM ('queued', 'running', 'done', 'dead');
+
+-- durable background work (transcription, summarization, ...), claimed with `FOR UPDATE SKIP LOCKED`
+CREATE TABLE job (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    kind TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    status job_status NOT NULL DEFAULT 'queued',
+    attempts INT NOT NULL DEFAULT 0,
+    max_attempts INT NOT NULL DEFAULT 5,
+    run_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    locked_at TIMESTAMPTZ,
+    locked_by TEXT,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX job_queued_run_at_idx ON job (run_at) WHERE status = 'queued';
+CREATE INDEX job_running_locked_at_idx ON job (locked_at) WHERE status = 'running';
@@ -0,0 +1,14 @@
+CREATE TYPE diary_processing_status AS ENUM (
+    'uploaded',
+    'transcribing',
+    'summarizing',
+    'complete',
+    'failed'
+);
+
+ALTER TABLE diary
+    ADD COLUMN processing_status diary_processing_status NOT NULL DEFAULT 'uploaded',
+    ADD COLUMN processing_error TEXT;
+
+-- diaries processed before the status existed
+UPDATE diary SET processing_status = 'complete' WHERE summary IS NOT NULL;
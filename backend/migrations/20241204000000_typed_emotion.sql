@@ -0,0 +1,25 @@
+CREATE TYPE emotion AS ENUM (
+    'anger',
+    'sadness',
+    'happiness',
+    'neutral',
+    'anxiety',
+    'gratitude',
+    'excitement',
+    'tiredness',
+    'loneliness',
+    'calm'
+);
+
+-- free-form model output that doesn't name a known label is dropped
+ALTER TABLE diary
+    ALTER COLUMN emotion TYPE emotion USING (
+        CASE
+            WHEN lower(trim(emotion)) IN (
+                'anger', 'sadness', 'happiness', 'neutral', 'anxiety',
+                'gratitude', 'excitement', 'tiredness', 'loneliness', 'calm'
+            ) THEN lower(trim(emotion))::emotion
+            ELSE NULL
+        END
+    ),
+    ADD COLUMN emotion_scores JSONB;
@@ -0,0 +1,3 @@
+ALTER TABLE diary ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX diary_deleted_at_idx ON diary (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,6 @@
+-- objects are referenced by key and signed on read; the bucket follows from the table
+-- (diary -> audio, deco -> model). `audio_link`/`asset_link` hold the old presigned
+-- links until `cargo run --bin migrate_links` rewrites them into keys.
+ALTER TABLE diary ADD COLUMN IF NOT EXISTS audio_key TEXT;
+ALTER TABLE deco ADD COLUMN IF NOT EXISTS asset_key TEXT;
+ALTER TABLE deco ALTER COLUMN asset_link DROP NOT NULL;
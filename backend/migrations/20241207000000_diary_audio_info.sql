@@ -0,0 +1,4 @@
+-- properties of the stored recording, probed on upload
+ALTER TABLE diary ADD COLUMN IF NOT EXISTS duration_ms INTEGER;
+ALTER TABLE diary ADD COLUMN IF NOT EXISTS sample_rate INTEGER;
+ALTER TABLE diary ADD COLUMN IF NOT EXISTS codec TEXT;
@@ -0,0 +1,9 @@
+-- timed pieces of a diary's transcription, in order of `idx`
+CREATE TABLE IF NOT EXISTS diary_segment (
+    diary_id BIGINT NOT NULL REFERENCES diary (id) ON DELETE CASCADE,
+    idx INTEGER NOT NULL,
+    start_ms INTEGER NOT NULL,
+    end_ms INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    PRIMARY KEY (diary_id, idx)
+);
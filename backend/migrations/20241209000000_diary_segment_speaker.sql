@@ -0,0 +1,2 @@
+-- who is speaking in a segment, for transcription backends that tell speakers apart
+ALTER TABLE diary_segment ADD COLUMN IF NOT EXISTS speaker TEXT;
@@ -0,0 +1,7 @@
+-- substring search over what was said and its summary. Korean attaches
+-- particles to words, so trigrams match "학교" in "학교에서" where a
+-- whitespace tokenizer would not.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS diary_search_trgm_idx ON diary
+    USING GIN ((coalesce(summary, '') || ' ' || coalesce(transcription, '')) gin_trgm_ops);
@@ -0,0 +1,8 @@
+-- one embedding per diary, compared by brute-force cosine similarity in the
+-- app; `model` keeps vectors of different embedders apart
+CREATE TABLE IF NOT EXISTS diary_embedding (
+    diary_id BIGINT PRIMARY KEY REFERENCES diary (id) ON DELETE CASCADE,
+    model TEXT NOT NULL,
+    embedding REAL[] NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
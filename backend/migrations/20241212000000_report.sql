@@ -0,0 +1,18 @@
+CREATE TYPE report_period AS ENUM ('week', 'month');
+
+-- reflection digests of a user's week (starting on monday) or month, over
+-- the `local_date`s of their diaries; `fingerprint` hashes the diaries it was
+-- written from, to tell when it is stale
+CREATE TABLE IF NOT EXISTS report (
+    user_id UUID NOT NULL,
+    period report_period NOT NULL,
+    period_start DATE NOT NULL,
+    period_end DATE NOT NULL,
+    diary_count INTEGER NOT NULL,
+    emotion_counts JSONB NOT NULL,
+    digest JSONB NOT NULL,
+    fingerprint TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, period, period_start)
+);
@@ -0,0 +1,6 @@
+-- cells a deco occupies in the room grid before rotation: `width` along x,
+-- `depth` along z and `height` along y (up)
+ALTER TABLE deco
+    ADD COLUMN width INTEGER NOT NULL DEFAULT 1 CHECK (width > 0),
+    ADD COLUMN depth INTEGER NOT NULL DEFAULT 1 CHECK (depth > 0),
+    ADD COLUMN height INTEGER NOT NULL DEFAULT 1 CHECK (height > 0);
@@ -0,0 +1,10 @@
+-- one row per user and month once its layout is first changed;
+-- `layout_version` goes up with every change, for optimistic concurrency
+CREATE TABLE IF NOT EXISTS room (
+    user_id UUID NOT NULL,
+    year INTEGER NOT NULL,
+    month INTEGER NOT NULL CHECK (month BETWEEN 1 AND 12),
+    layout_version BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, year, month)
+);
@@ -0,0 +1,15 @@
+-- the placements of a room after each layout version, to look back and restore
+CREATE TABLE IF NOT EXISTS room_layout_snapshot (
+    user_id UUID NOT NULL,
+    year INTEGER NOT NULL,
+    month INTEGER NOT NULL,
+    version BIGINT NOT NULL,
+    -- [{diary_id, deco_id, coordinates}]
+    placements JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, year, month, version),
+    FOREIGN KEY (user_id, year, month) REFERENCES room (user_id, year, month) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS room_layout_snapshot_user_created_at_idx
+    ON room_layout_snapshot (user_id, created_at DESC, version DESC);
@@ -0,0 +1,7 @@
+-- the look of a room, picked from catalog decos of the matching category;
+-- a `room_size` deco's footprint is the size of the room
+ALTER TABLE room
+    ADD COLUMN IF NOT EXISTS wallpaper_id BIGINT REFERENCES deco (id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS floor_id BIGINT REFERENCES deco (id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS lighting_id BIGINT REFERENCES deco (id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS size_id BIGINT REFERENCES deco (id) ON DELETE SET NULL;
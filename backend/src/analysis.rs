@@ -0,0 +1,6 @@
+pub mod analyzer;
+pub mod ask;
+pub mod config;
+pub mod emotion;
+pub mod mock;
+pub mod report;
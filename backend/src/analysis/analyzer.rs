@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+
+use crate::openai::chat::ChatAnalyzer;
+
+use super::{
+    ask::{DiaryAnswer, DiaryExcerpt},
+    config::AnalyzerConfig,
+    emotion::EmotionAnalysis,
+    mock::ScriptedAnalyzer,
+    report::{ReflectionDigest, ReportEntry},
+};
+
+/// LLM backend used to summarize diaries, classify their sentiment, answer
+/// questions about them and reflect on a week or month of them.
+#[async_trait]
+pub trait TextAnalyzer: Send + Sync {
+    async fn summarize(&self, content: &str) -> anyhow::Result<String>;
+    async fn sentiment(&self, content: &str) -> anyhow::Result<EmotionAnalysis>;
+    // answers from the excerpts only, citing the ones it used
+    async fn answer(
+        &self,
+        question: &str,
+        excerpts: &[DiaryExcerpt],
+    ) -> anyhow::Result<DiaryAnswer>;
+    // `period` describes the range, e.g. "the week of 2024-12-02"
+    async fn reflect(
+        &self,
+        period: &str,
+        entries: &[ReportEntry],
+    ) -> anyhow::Result<ReflectionDigest>;
+}
+
+/// Picks the backend named by `ANALYZER` (`openai`, `openai_compatible` or `mock`).
+pub fn analyzer_from_env() -> anyhow::Result<Arc<dyn TextAnalyzer>> {
+    let backend = std::env::var("ANALYZER").unwrap_or_else(|_| "openai".to_string());
+    let config = AnalyzerConfig::from_env();
+    match backend.as_str() {
+        "openai" => Ok(Arc::new(ChatAnalyzer::openai(config)?)),
+        "openai_compatible" => {
+            let base_url = std::env::var("LLM_BASE_URL").map_err(|_| {
+                anyhow!("LLM_BASE_URL must be set for the openai_compatible backend")
+            })?;
+            // local servers usually accept any key
+            let api_key = std::env::var("LLM_API_KEY").unwrap_or_default();
+            Ok(Arc::new(ChatAnalyzer::compatible(
+                base_url, api_key, config,
+            )?))
+        }
+        "mock" => Ok(Arc::new(ScriptedAnalyzer::default())),
+        other => Err(anyhow!("Unknown ANALYZER backend: {}", other)),
+    }
+}
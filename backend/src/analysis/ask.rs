@@ -0,0 +1,69 @@
+use anyhow::anyhow;
+use serde::Deserialize;
+use time::Date;
+
+/// A retrieved diary handed to the model as context for a question.
+#[derive(Clone, Debug)]
+pub struct DiaryExcerpt {
+    pub diary_id: i64,
+    pub local_date: Date,
+    pub text: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct DiaryAnswer {
+    pub answer: String,
+    // ids of the excerpts the answer relies on
+    pub citations: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+struct RawDiaryAnswer {
+    answer: String,
+    #[serde(default)]
+    citations: Vec<i64>,
+}
+
+pub fn render_excerpts(excerpts: &[DiaryExcerpt]) -> String {
+    excerpts
+        .iter()
+        .map(|excerpt| {
+            format!(
+                "[diary_id={}, date={}]\n{}",
+                excerpt.diary_id, excerpt.local_date, excerpt.text
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Parses the model's JSON answer. Citations of diaries that were not among
+/// `excerpts` are dropped, so the answer can only point at what it was shown.
+pub fn parse_diary_answer(raw: &str, excerpts: &[DiaryExcerpt]) -> anyhow::Result<DiaryAnswer> {
+    // some models wrap json mode output in a code fence anyway
+    let raw = raw
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```");
+    let parsed: RawDiaryAnswer = serde_json::from_str(raw)?;
+    if parsed.answer.trim().is_empty() {
+        return Err(anyhow!("Empty answer"));
+    }
+
+    let mut citations = Vec::new();
+    for diary_id in parsed.citations {
+        if !excerpts.iter().any(|excerpt| excerpt.diary_id == diary_id) {
+            tracing::warn!(
+                "Dropping citation of diary {} that was not retrieved",
+                diary_id
+            );
+        } else if !citations.contains(&diary_id) {
+            citations.push(diary_id);
+        }
+    }
+    Ok(DiaryAnswer {
+        answer: parsed.answer.trim().to_string(),
+        citations,
+    })
+}
@@ -0,0 +1,102 @@
+const DEFAULT_SUMMARY_PROMPT: &str = "Summarize the following text in korean: {text}";
+const DEFAULT_SENTIMENT_PROMPT: &str = "Analyze the emotions of the following korean text.
+        Respond only with a JSON object of the form
+        {\"emotion\": <label>, \"scores\": {<label>: <confidence between 0 and 1>, ...}}
+        using only these labels: {labels}. \"emotion\" must be the label with the highest score.
+        Target text: {text}";
+const DEFAULT_ASK_PROMPT: &str = "You answer questions about the user's own voice diary.
+        Use only the diary entries below; if they do not answer the question, say so.
+        Answer in the language of the question and mention dates where they help.
+        Respond only with a JSON object of the form
+        {\"answer\": <answer>, \"citations\": [<diary_id of every entry the answer relies on>, ...]}.
+        Entries:
+        {diaries}
+        Question: {question}";
+const DEFAULT_REPORT_PROMPT: &str = "Below are the summaries of the user's voice diary entries for {period},
+        each with its date and the emotion detected in it.
+        Write a warm, reflective digest in korean addressed to the user.
+        Respond only with a JSON object of the form
+        {\"overview\": <a short paragraph>, \"highlights\": [<notable moments>, ...],
+        \"themes\": [<recurring themes>, ...], \"mood_trajectory\": <how the mood changed over the period>}.
+        Entries:
+        {diaries}";
+
+/// Model parameters and prompt templates shared by the chat-completion backends.
+/// Templates use `{text}` as the placeholder for the diary text; the sentiment
+/// template may also use `{labels}` for the list of emotion labels. The ask
+/// template uses `{diaries}` for the retrieved entries and `{question}`, the
+/// report template `{diaries}` and `{period}`.
+#[derive(Clone, Debug)]
+pub struct AnalyzerConfig {
+    pub model: String,
+    pub temperature: f64,
+    pub max_tokens: i64,
+    pub summary_prompt: String,
+    pub sentiment_prompt: String,
+    // how many times to ask again when the sentiment answer is invalid
+    pub sentiment_attempts: u32,
+    pub ask_prompt: String,
+    // answers run longer than summaries
+    pub ask_max_tokens: i64,
+    pub report_prompt: String,
+    pub report_max_tokens: i64,
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> Self {
+        Self {
+            model: "gpt-3.5-turbo".to_string(),
+            temperature: 0.0,
+            max_tokens: 50,
+            summary_prompt: DEFAULT_SUMMARY_PROMPT.to_string(),
+            sentiment_prompt: DEFAULT_SENTIMENT_PROMPT.to_string(),
+            sentiment_attempts: 3,
+            ask_prompt: DEFAULT_ASK_PROMPT.to_string(),
+            ask_max_tokens: 500,
+            report_prompt: DEFAULT_REPORT_PROMPT.to_string(),
+            report_max_tokens: 1000,
+        }
+    }
+}
+
+impl AnalyzerConfig {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            model: std::env::var("LLM_MODEL").unwrap_or(default.model),
+            temperature: std::env::var("LLM_TEMPERATURE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.temperature),
+            max_tokens: std::env::var("LLM_MAX_TOKENS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.max_tokens),
+            summary_prompt: std::env::var("LLM_SUMMARY_PROMPT").unwrap_or(default.summary_prompt),
+            sentiment_prompt: std::env::var("LLM_SENTIMENT_PROMPT")
+                .unwrap_or(default.sentiment_prompt),
+            sentiment_attempts: std::env::var("LLM_SENTIMENT_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.sentiment_attempts),
+            ask_prompt: std::env::var("LLM_ASK_PROMPT").unwrap_or(default.ask_prompt),
+            ask_max_tokens: std::env::var("LLM_ASK_MAX_TOKENS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.ask_max_tokens),
+            report_prompt: std::env::var("LLM_REPORT_PROMPT").unwrap_or(default.report_prompt),
+            report_max_tokens: std::env::var("LLM_REPORT_MAX_TOKENS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.report_max_tokens),
+        }
+    }
+}
+
+pub fn render_prompt(template: &str, text: &str) -> String {
+    if template.contains("{text}") {
+        template.replace("{text}", text)
+    } else {
+        format!("{}{}", template, text)
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    sqlx::Type, Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[sqlx(type_name = "emotion", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Emotion {
+    Anger,
+    Sadness,
+    Happiness,
+    Neutral,
+    Anxiety,
+    Gratitude,
+    Excitement,
+    Tiredness,
+    Loneliness,
+    Calm,
+}
+
+impl Emotion {
+    pub const ALL: [Emotion; 10] = [
+        Emotion::Anger,
+        Emotion::Sadness,
+        Emotion::Happiness,
+        Emotion::Neutral,
+        Emotion::Anxiety,
+        Emotion::Gratitude,
+        Emotion::Excitement,
+        Emotion::Tiredness,
+        Emotion::Loneliness,
+        Emotion::Calm,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Emotion::Anger => "anger",
+            Emotion::Sadness => "sadness",
+            Emotion::Happiness => "happiness",
+            Emotion::Neutral => "neutral",
+            Emotion::Anxiety => "anxiety",
+            Emotion::Gratitude => "gratitude",
+            Emotion::Excitement => "excitement",
+            Emotion::Tiredness => "tiredness",
+            Emotion::Loneliness => "loneliness",
+            Emotion::Calm => "calm",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Emotion> {
+        let label = label.trim().to_lowercase();
+        Emotion::ALL.into_iter().find(|e| e.label() == label)
+    }
+}
+
+/// Confidence in `[0, 1]` per label.
+pub type EmotionScores = BTreeMap<Emotion, f32>;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct EmotionAnalysis {
+    pub emotion: Emotion,
+    pub scores: EmotionScores,
+}
+
+#[derive(Deserialize)]
+struct RawEmotionAnalysis {
+    emotion: String,
+    scores: HashMap<String, f32>,
+}
+
+/// Parses and validates the model's JSON answer; anything unexpected is an error
+/// so the caller can ask again instead of persisting it.
+pub fn parse_emotion_analysis(raw: &str) -> anyhow::Result<EmotionAnalysis> {
+    // some models wrap json mode output in a code fence anyway
+    let raw = raw
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```");
+    let parsed: RawEmotionAnalysis = serde_json::from_str(raw)?;
+
+    let emotion = Emotion::from_label(&parsed.emotion)
+        .ok_or_else(|| anyhow!("Unknown emotion label: {}", parsed.emotion))?;
+    let mut scores = EmotionScores::new();
+    for (label, confidence) in parsed.scores {
+        let label_emotion = Emotion::from_label(&label)
+            .ok_or_else(|| anyhow!("Unknown emotion label: {}", label))?;
+        if !(0.0..=1.0).contains(&confidence) {
+            return Err(anyhow!(
+                "Confidence out of range for {}: {}",
+                label,
+                confidence
+            ));
+        }
+        scores.insert(label_emotion, confidence);
+    }
+
+    let Some(&primary) = scores.get(&emotion) else {
+        return Err(anyhow!("No confidence given for {}", emotion.label()));
+    };
+    if scores.values().any(|&confidence| confidence > primary) {
+        return Err(anyhow!(
+            "{} is not the highest scoring emotion",
+            emotion.label()
+        ));
+    }
+    Ok(EmotionAnalysis { emotion, scores })
+}
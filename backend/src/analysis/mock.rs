@@ -0,0 +1,84 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use async_trait::async_trait;
+
+use super::{
+    analyzer::TextAnalyzer,
+    ask::{DiaryAnswer, DiaryExcerpt},
+    emotion::{Emotion, EmotionAnalysis, EmotionScores},
+    report::{ReflectionDigest, ReportEntry},
+};
+
+/// Replays queued responses in order, then falls back to fixed defaults.
+pub struct ScriptedAnalyzer {
+    summaries: Mutex<VecDeque<String>>,
+    sentiments: Mutex<VecDeque<EmotionAnalysis>>,
+    default_summary: String,
+    default_sentiment: EmotionAnalysis,
+}
+
+impl ScriptedAnalyzer {
+    pub fn new(summaries: Vec<String>, sentiments: Vec<EmotionAnalysis>) -> Self {
+        Self {
+            summaries: Mutex::new(summaries.into()),
+            sentiments: Mutex::new(sentiments.into()),
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for ScriptedAnalyzer {
+    fn default() -> Self {
+        Self {
+            summaries: Mutex::new(VecDeque::new()),
+            sentiments: Mutex::new(VecDeque::new()),
+            default_summary: "summary".to_string(),
+            default_sentiment: EmotionAnalysis {
+                emotion: Emotion::Neutral,
+                scores: EmotionScores::from([(Emotion::Neutral, 1.0)]),
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl TextAnalyzer for ScriptedAnalyzer {
+    async fn summarize(&self, _content: &str) -> anyhow::Result<String> {
+        let next = self.summaries.lock().unwrap().pop_front();
+        Ok(next.unwrap_or_else(|| self.default_summary.clone()))
+    }
+
+    async fn sentiment(&self, _content: &str) -> anyhow::Result<EmotionAnalysis> {
+        let next = self.sentiments.lock().unwrap().pop_front();
+        Ok(next.unwrap_or_else(|| self.default_sentiment.clone()))
+    }
+
+    // cites every excerpt, so callers can check what was retrieved
+    async fn answer(
+        &self,
+        question: &str,
+        excerpts: &[DiaryExcerpt],
+    ) -> anyhow::Result<DiaryAnswer> {
+        Ok(DiaryAnswer {
+            answer: format!("{} diaries about: {}", excerpts.len(), question),
+            citations: excerpts.iter().map(|excerpt| excerpt.diary_id).collect(),
+        })
+    }
+
+    async fn reflect(
+        &self,
+        period: &str,
+        entries: &[ReportEntry],
+    ) -> anyhow::Result<ReflectionDigest> {
+        Ok(ReflectionDigest {
+            overview: format!("{} diaries in {}", entries.len(), period),
+            highlights: entries.iter().map(|entry| entry.summary.clone()).collect(),
+            themes: vec![],
+            mood_trajectory: entries
+                .iter()
+                .map(|entry| entry.emotion.map(|e| e.label()).unwrap_or("unknown"))
+                .collect::<Vec<_>>()
+                .join(" -> "),
+        })
+    }
+}
@@ -0,0 +1,55 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use time::Date;
+
+use super::emotion::Emotion;
+
+/// One summarized diary of the period a report covers.
+#[derive(Clone, Debug)]
+pub struct ReportEntry {
+    pub local_date: Date,
+    pub emotion: Option<Emotion>,
+    pub summary: String,
+}
+
+/// The model's reflection on a period, stored as is in `report.digest`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ReflectionDigest {
+    pub overview: String,
+    #[serde(default)]
+    pub highlights: Vec<String>,
+    #[serde(default)]
+    pub themes: Vec<String>,
+    // how the mood moved over the period, in prose
+    pub mood_trajectory: String,
+}
+
+pub fn render_entries(entries: &[ReportEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "[{}, {}] {}",
+                entry.local_date,
+                entry.emotion.map(|e| e.label()).unwrap_or("unknown"),
+                entry.summary
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses and validates the model's JSON reflection.
+pub fn parse_reflection(raw: &str) -> anyhow::Result<ReflectionDigest> {
+    // some models wrap json mode output in a code fence anyway
+    let raw = raw
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```");
+    let digest: ReflectionDigest = serde_json::from_str(raw)?;
+    if digest.overview.trim().is_empty() {
+        return Err(anyhow!("Empty overview"));
+    }
+    Ok(digest)
+}
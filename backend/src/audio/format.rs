@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+/// Container formats accepted for diary recordings, detected from the file's
+/// leading bytes rather than the client-provided name or content type.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFormat {
+    // MPEG-4 audio, usually AAC (iOS recordings)
+    M4a,
+    Mp3,
+    Wav,
+    // Ogg with Opus or Vorbis (Android)
+    Ogg,
+    // Matroska/WebM with Opus (browsers' MediaRecorder)
+    Webm,
+}
+
+// enough leading bytes to tell every supported format apart
+pub const SNIFF_LEN: usize = 12;
+
+impl AudioFormat {
+    pub fn sniff(header: &[u8]) -> Option<Self> {
+        match header {
+            [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some(AudioFormat::M4a),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => {
+                Some(AudioFormat::Wav)
+            }
+            [b'O', b'g', b'g', b'S', ..] => Some(AudioFormat::Ogg),
+            [0x1A, 0x45, 0xDF, 0xA3, ..] => Some(AudioFormat::Webm),
+            [b'I', b'D', b'3', ..] => Some(AudioFormat::Mp3),
+            // MPEG audio frame sync; layer bits of 00 would be raw ADTS AAC
+            [0xFF, second, ..] if second & 0xE0 == 0xE0 && second & 0x06 != 0 => {
+                Some(AudioFormat::Mp3)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Webm => "webm",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            AudioFormat::M4a => "audio/mp4",
+            AudioFormat::Mp3 => "audio/mpeg",
+            AudioFormat::Wav => "audio/wav",
+            AudioFormat::Ogg => "audio/ogg",
+            AudioFormat::Webm => "audio/webm",
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::{anyhow, Context};
+use tempfile::NamedTempFile;
+use tokio::{io::AsyncReadExt, process::Command};
+
+use super::{
+    format::{AudioFormat, SNIFF_LEN},
+    probe::{probe_audio, AudioInfo},
+};
+
+#[derive(Debug)]
+pub enum AudioError {
+    // not one of the accepted formats, or not audio at all
+    Unsupported(String),
+    // a supported container that ffprobe cannot read
+    Corrupt(String),
+    Internal(anyhow::Error),
+}
+
+impl std::fmt::Display for AudioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AudioError::Unsupported(m) | AudioError::Corrupt(m) => write!(f, "{}", m),
+            AudioError::Internal(e) => write!(f, "{:#}", e),
+        }
+    }
+}
+
+impl From<anyhow::Error> for AudioError {
+    fn from(e: anyhow::Error) -> Self {
+        AudioError::Internal(e)
+    }
+}
+
+impl From<std::io::Error> for AudioError {
+    fn from(e: std::io::Error) -> Self {
+        AudioError::Internal(e.into())
+    }
+}
+
+/// An upload that passed validation, ready to be stored.
+pub struct ProcessedAudio {
+    pub file: NamedTempFile,
+    pub format: AudioFormat,
+    // `None` when probing is disabled
+    pub info: Option<AudioInfo>,
+}
+
+/// Validates uploaded recordings and optionally transcodes them to AAC in an
+/// m4a container, the format iOS records in.
+pub struct AudioPipeline {
+    // ffprobe binary, probing is skipped when unset
+    ffprobe: Option<String>,
+    ffmpeg: String,
+    transcode: bool,
+}
+
+impl AudioPipeline {
+    pub fn from_env() -> Self {
+        let flag = |key: &str, default: bool| {
+            std::env::var(key)
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(default)
+        };
+        let transcode = flag("AUDIO_TRANSCODE", false);
+        let probe = flag("AUDIO_PROBE", true);
+        if transcode && !probe {
+            tracing::warn!("AUDIO_TRANSCODE needs AUDIO_PROBE, uploads are stored as is");
+        }
+        Self {
+            ffprobe: probe
+                .then(|| std::env::var("FFPROBE_BIN").unwrap_or_else(|_| "ffprobe".to_string())),
+            ffmpeg: std::env::var("FFMPEG_BIN").unwrap_or_else(|_| "ffmpeg".to_string()),
+            transcode: transcode && probe,
+        }
+    }
+
+    pub async fn process(&self, upload: NamedTempFile) -> Result<ProcessedAudio, AudioError> {
+        let mut header = Vec::with_capacity(SNIFF_LEN);
+        tokio::fs::File::open(upload.path())
+            .await?
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut header)
+            .await?;
+        let format = AudioFormat::sniff(&header).ok_or_else(|| {
+            AudioError::Unsupported(
+                "Unsupported audio format, expected m4a, mp3, wav, ogg or webm".to_string(),
+            )
+        })?;
+
+        let Some(ffprobe) = &self.ffprobe else {
+            return Ok(ProcessedAudio {
+                file: upload,
+                format,
+                info: None,
+            });
+        };
+        let info = probe_audio(ffprobe, upload.path()).await?.ok_or_else(|| {
+            AudioError::Corrupt(format!(
+                "The uploaded {} file is corrupt or has no audio",
+                format.extension()
+            ))
+        })?;
+
+        if !self.transcode || (format == AudioFormat::M4a && info.codec == "aac") {
+            return Ok(ProcessedAudio {
+                file: upload,
+                format,
+                info: Some(info),
+            });
+        }
+        let transcoded = self.transcode_to_m4a(&upload).await?;
+        let info = probe_audio(ffprobe, transcoded.path())
+            .await?
+            .ok_or_else(|| anyhow!("ffmpeg produced an unreadable file"))?;
+        Ok(ProcessedAudio {
+            file: transcoded,
+            format: AudioFormat::M4a,
+            info: Some(info),
+        })
+    }
+
+    async fn transcode_to_m4a(&self, upload: &NamedTempFile) -> anyhow::Result<NamedTempFile> {
+        // ffmpeg picks the container from the extension
+        let output_file = tempfile::Builder::new().suffix(".m4a").tempfile()?;
+        let output = Command::new(&self.ffmpeg)
+            .args(["-nostdin", "-loglevel", "error", "-y", "-i"])
+            .arg(upload.path())
+            .args([
+                "-vn",
+                "-c:a",
+                "aac",
+                "-b:a",
+                "96k",
+                "-movflags",
+                "+faststart",
+            ])
+            .arg(output_file.path())
+            .output()
+            .await
+            .with_context(|| format!("failed to run {}", self.ffmpeg))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "ffmpeg failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(output_file)
+    }
+}
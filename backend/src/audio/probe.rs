@@ -0,0 +1,137 @@
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// Stream properties of a recording as reported by ffprobe.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct AudioInfo {
+    pub duration_ms: i32,
+    pub sample_rate: i32,
+    pub codec: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProbeStream {
+    codec_name: Option<String>,
+    sample_rate: Option<String>,
+    duration: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProbeFormat {
+    duration: Option<String>,
+}
+
+/// Runs ffprobe on the first audio stream. `Ok(None)` means ffprobe could not
+/// make sense of the file, i.e. it is corrupt or has no audio.
+pub async fn probe_audio(ffprobe: &str, path: &Path) -> anyhow::Result<Option<AudioInfo>> {
+    let output = Command::new(ffprobe)
+        .args([
+            "-v",
+            "error",
+            "-print_format",
+            "json",
+            "-show_streams",
+            "-show_format",
+            "-select_streams",
+            "a:0",
+        ])
+        .arg(path)
+        .output()
+        .await
+        .with_context(|| format!("failed to run {}", ffprobe))?;
+    if !output.status.success() {
+        tracing::debug!(
+            "ffprobe rejected {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return Ok(None);
+    }
+
+    let probe: ProbeOutput = serde_json::from_slice(&output.stdout)?;
+    let Some(stream) = probe.streams.into_iter().next() else {
+        return Ok(None);
+    };
+    // webm only carries the duration on the container, if at all
+    let duration = match stream
+        .duration
+        .or(probe.format.and_then(|format| format.duration))
+        .and_then(|d| d.parse::<f64>().ok())
+    {
+        Some(duration) => Some(duration),
+        None => packet_duration(ffprobe, path).await?,
+    };
+    let sample_rate = stream.sample_rate.and_then(|r| r.parse::<i32>().ok());
+    match (duration, sample_rate, stream.codec_name) {
+        (Some(duration), Some(sample_rate), Some(codec)) if duration > 0.0 => Ok(Some(AudioInfo {
+            duration_ms: (duration * 1000.0).round() as i32,
+            sample_rate,
+            codec,
+        })),
+        _ => Ok(None),
+    }
+}
+
+/// The end of the last audio packet in seconds, for recordings whose headers
+/// carry no duration, like those of browsers' MediaRecorder. Reads through the
+/// whole file, but without decoding it.
+async fn packet_duration(ffprobe: &str, path: &Path) -> anyhow::Result<Option<f64>> {
+    let output = Command::new(ffprobe)
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "a:0",
+            "-show_entries",
+            "packet=pts_time,duration_time",
+            "-of",
+            "csv=p=0",
+        ])
+        .arg(path)
+        .output()
+        .await
+        .with_context(|| format!("failed to run {}", ffprobe))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(last_packet_end(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// lines of `pts_time,duration_time`, either of which can be `N/A`
+fn last_packet_end(packets: &str) -> Option<f64> {
+    packets
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line
+                .split(',')
+                .map(|field| field.trim().parse::<f64>().ok());
+            let pts = fields.next()??;
+            Some(pts + fields.next().flatten().unwrap_or(0.0))
+        })
+        .reduce(f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_packet_end_reads_ffprobe_csv() {
+        let packets = "0.000000,0.020000\n0.020000,0.020000\n4.980000,0.020000\n";
+        assert_eq!(last_packet_end(packets), Some(5.0));
+        // webm packets often come without a duration
+        assert_eq!(last_packet_end("0.000000,N/A\n3.500000,N/A\n"), Some(3.5));
+        assert_eq!(last_packet_end("N/A,N/A\n"), None);
+        assert_eq!(last_packet_end(""), None);
+    }
+}
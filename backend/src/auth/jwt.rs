@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use axum::http::StatusCode;
+use jsonwebtoken::{
+    decode, decode_header, errors::ErrorKind, jwk::JwkSet, Algorithm, DecodingKey, Validation,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+// role supabase assigns to signed-in users; `anon` and `service_role` keys are rejected
+const AUTHENTICATED_ROLE: &str = "authenticated";
+
+#[derive(Deserialize, Debug)]
+pub struct Claims {
+    pub sub: Uuid,
+    pub exp: u64,
+    pub role: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    MissingToken,
+    InvalidToken,
+    ExpiredToken,
+    Forbidden,
+}
+
+impl AuthError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AuthError::Forbidden => StatusCode::FORBIDDEN,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            AuthError::MissingToken => "Missing bearer token",
+            AuthError::InvalidToken => "Invalid token",
+            AuthError::ExpiredToken => "Token has expired",
+            AuthError::Forbidden => "Token is not allowed to access this resource",
+        }
+    }
+}
+
+enum VerifierKeys {
+    // HS256 with the project's shared JWT secret
+    Secret(DecodingKey),
+    // RS256 keys from a JWKS file, looked up by `kid`
+    Jwks(HashMap<String, DecodingKey>),
+}
+
+pub struct JwtVerifier {
+    keys: VerifierKeys,
+    audience: String,
+}
+
+impl JwtVerifier {
+    pub fn from_secret(secret: &[u8], audience: String) -> Self {
+        Self {
+            keys: VerifierKeys::Secret(DecodingKey::from_secret(secret)),
+            audience,
+        }
+    }
+
+    pub fn from_jwks(jwks: &JwkSet, audience: String) -> anyhow::Result<Self> {
+        let mut keys = HashMap::new();
+        for jwk in &jwks.keys {
+            let Some(kid) = jwk.common.key_id.clone() else {
+                continue;
+            };
+            keys.insert(kid, DecodingKey::from_jwk(jwk)?);
+        }
+        if keys.is_empty() {
+            return Err(anyhow!("JWKS does not contain any key with a `kid`"));
+        }
+        Ok(Self {
+            keys: VerifierKeys::Jwks(keys),
+            audience,
+        })
+    }
+
+    /// Reads `SUPABASE_JWKS_PATH` (RS256) or falls back to `SUPABASE_JWT_SECRET` (HS256).
+    pub fn from_env() -> anyhow::Result<Self> {
+        let audience =
+            std::env::var("JWT_AUDIENCE").unwrap_or_else(|_| AUTHENTICATED_ROLE.to_string());
+        if let Ok(jwks_path) = std::env::var("SUPABASE_JWKS_PATH") {
+            let jwks: JwkSet = serde_json::from_slice(&std::fs::read(jwks_path)?)?;
+            return Self::from_jwks(&jwks, audience);
+        }
+        let secret = std::env::var("SUPABASE_JWT_SECRET")
+            .map_err(|_| anyhow!("SUPABASE_JWT_SECRET or SUPABASE_JWKS_PATH must be set"))?;
+        Ok(Self::from_secret(secret.as_bytes(), audience))
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, AuthError> {
+        let (key, algorithm) = match &self.keys {
+            VerifierKeys::Secret(key) => (key, Algorithm::HS256),
+            VerifierKeys::Jwks(keys) => {
+                let header = decode_header(token).map_err(|_| AuthError::InvalidToken)?;
+                let key = header
+                    .kid
+                    .and_then(|kid| keys.get(&kid))
+                    .ok_or(AuthError::InvalidToken)?;
+                (key, Algorithm::RS256)
+            }
+        };
+
+        let mut validation = Validation::new(algorithm);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "sub", "aud"]);
+
+        let claims = decode::<Claims>(token, key, &validation)
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => AuthError::ExpiredToken,
+                _ => AuthError::InvalidToken,
+            })?
+            .claims;
+
+        if claims.role.as_deref() != Some(AUTHENTICATED_ROLE) {
+            return Err(AuthError::Forbidden);
+        }
+        Ok(claims)
+    }
+}
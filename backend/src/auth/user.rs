@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{header::AUTHORIZATION, request::Parts},
+};
+use uuid::Uuid;
+
+use super::jwt::{AuthError, JwtVerifier};
+use crate::error::AppError;
+
+/// The id of the caller, taken from a verified bearer token.
+#[derive(Clone, Copy, Debug)]
+pub struct AuthUser(pub Uuid);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthUser
+where
+    Arc<JwtVerifier>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let verifier = Arc::<JwtVerifier>::from_ref(state);
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(AuthError::MissingToken)?;
+
+        let claims = verifier.verify(token.trim())?;
+        Ok(AuthUser(claims.sub))
+    }
+}
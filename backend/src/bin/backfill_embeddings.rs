@@ -0,0 +1,56 @@
+//! Queues an embedding job for every finished diary that has no embedding of
+//! the configured `EMBEDDER` model yet, e.g. diaries from before similar
+//! diaries existed or after switching models. Safe to run more than once.
+//!
+//! `cargo run --bin backfill_embeddings [-- --dry-run]`
+use recordiary::{
+    db::{conn::initialize_conn_pool, job::enqueue_job},
+    embedding::embedder::embedder_from_env,
+    jobs::kind::Job,
+};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "backfill_embeddings=info".into()),
+        )
+        .init();
+    let dry_run = std::env::args().any(|arg| arg == "--dry-run");
+
+    let model = embedder_from_env()?.model().to_string();
+    let pool = initialize_conn_pool().await;
+    let diary_ids = sqlx::query_scalar!(
+        r#"
+        SELECT d.id FROM diary d
+        LEFT JOIN diary_embedding e ON e.diary_id = d.id AND e.model = $1
+        WHERE d.processing_status = 'complete' AND d.deleted_at IS NULL AND e.diary_id IS NULL
+        ORDER BY d.id
+        "#,
+        model
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    if !dry_run {
+        let mut tx = pool.begin().await?;
+        for diary_id in &diary_ids {
+            enqueue_job(
+                &mut tx,
+                Job::EmbedDiary {
+                    diary_id: *diary_id,
+                },
+            )
+            .await?;
+        }
+        tx.commit().await?;
+    }
+    tracing::info!(
+        "{}{} diaries queued for {}",
+        if dry_run { "[dry run] " } else { "" },
+        diary_ids.len(),
+        model
+    );
+    Ok(())
+}
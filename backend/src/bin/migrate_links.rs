@@ -0,0 +1,111 @@
+//! One-off rewrite of the presigned links stored before objects were signed on
+//! read: fills `diary.audio_key` / `deco.asset_key` from `audio_link` /
+//! `asset_link` and clears the link. Safe to run more than once.
+//!
+//! `cargo run --bin migrate_links [-- --dry-run]`
+use percent_encoding::percent_decode_str;
+use recordiary::db::conn::initialize_conn_pool;
+use reqwest::Url;
+use sqlx::PgPool;
+
+/// Extracts the object key from a Supabase storage URL such as
+/// `https://x.supabase.co/storage/v1/object/sign/<bucket>/<key>?token=...`.
+fn object_key_from_link(link: &str) -> Option<String> {
+    let url = Url::parse(link).ok()?;
+    let segments = url
+        .path_segments()?
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let object = segments.iter().position(|segment| *segment == "object")?;
+    let mut rest = segments[object + 1..].iter().copied();
+    let mut bucket = rest.next()?;
+    if matches!(bucket, "sign" | "public" | "authenticated") {
+        bucket = rest.next()?;
+    }
+    tracing::trace!("Link {} points into bucket {}", link, bucket);
+    let key = rest
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/");
+    (!key.is_empty()).then_some(key)
+}
+
+async fn migrate_diaries(pool: &PgPool, dry_run: bool) -> anyhow::Result<(usize, usize)> {
+    let rows = sqlx::query!(
+        "SELECT id, audio_link as \"audio_link!\" FROM diary WHERE audio_link IS NOT NULL AND audio_key IS NULL"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut skipped = 0;
+    for row in &rows {
+        let Some(key) = object_key_from_link(&row.audio_link) else {
+            tracing::warn!("Diary {}: cannot parse link {}", row.id, row.audio_link);
+            skipped += 1;
+            continue;
+        };
+        tracing::info!("Diary {}: {}", row.id, key);
+        if !dry_run {
+            sqlx::query!(
+                "UPDATE diary SET audio_key = $1, audio_link = NULL WHERE id = $2",
+                key,
+                row.id
+            )
+            .execute(pool)
+            .await?;
+        }
+    }
+    Ok((rows.len() - skipped, skipped))
+}
+
+async fn migrate_decos(pool: &PgPool, dry_run: bool) -> anyhow::Result<(usize, usize)> {
+    let rows = sqlx::query!(
+        "SELECT id, asset_link as \"asset_link!\" FROM deco WHERE asset_link IS NOT NULL AND asset_key IS NULL"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut skipped = 0;
+    for row in &rows {
+        let Some(key) = object_key_from_link(&row.asset_link) else {
+            tracing::warn!("Deco {}: cannot parse link {}", row.id, row.asset_link);
+            skipped += 1;
+            continue;
+        };
+        tracing::info!("Deco {}: {}", row.id, key);
+        if !dry_run {
+            sqlx::query!(
+                "UPDATE deco SET asset_key = $1, asset_link = NULL WHERE id = $2",
+                key,
+                row.id
+            )
+            .execute(pool)
+            .await?;
+        }
+    }
+    Ok((rows.len() - skipped, skipped))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "migrate_links=info".into()),
+        )
+        .init();
+    let dry_run = std::env::args().any(|arg| arg == "--dry-run");
+
+    let pool = initialize_conn_pool().await;
+    let (diaries, skipped_diaries) = migrate_diaries(&pool, dry_run).await?;
+    let (decos, skipped_decos) = migrate_decos(&pool, dry_run).await?;
+    tracing::info!(
+        "{}{} diaries and {} decos rewritten, {} diaries and {} decos skipped",
+        if dry_run { "[dry run] " } else { "" },
+        diaries,
+        decos,
+        skipped_diaries,
+        skipped_decos
+    );
+    Ok(())
+}
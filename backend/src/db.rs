@@ -1,4 +1,10 @@
 pub mod conn;
 pub mod deco;
-pub mod user_deco;
 pub mod diary;
+pub mod diary_embedding;
+pub mod diary_segment;
+pub mod job;
+pub mod report;
+pub mod room;
+pub mod room_share;
+pub mod user_deco;
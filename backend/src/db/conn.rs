@@ -20,10 +20,18 @@ pub async fn initialize_conn_pool() -> Pool<Postgres> {
         .host(&db_host);
 
     // setup connection pool
-    PgPoolOptions::new()
-        .max_connections(5)
+    let pool = PgPoolOptions::new()
+        .max_connections(10)
         .acquire_timeout(Duration::from_secs(3))
         .connect_with(db_connection_opts)
         .await
-        .expect("can't connect to database")
+        .expect("can't connect to database");
+
+    // bring the schema up to date before serving any request
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("failed to run database migrations");
+
+    pool
 }
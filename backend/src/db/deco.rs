@@ -9,17 +9,32 @@ pub struct Deco {
     pub created_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
     pub updated_at: OffsetDateTime,
-    name: String,
-    asset_link: String,
-    category: Option<String>,
-    is_valid: bool,
+    pub name: String,
+    // signed into `asset_link` when served
+    #[serde(skip)]
+    pub asset_key: Option<String>,
+    pub category: Option<String>,
+    pub is_valid: bool,
     display_name: Option<String>,
+    // footprint in room cells
+    pub width: i32,
+    pub depth: i32,
+    pub height: i32,
 }
 
-pub async fn get_deco(tx: &mut PgConnection, deco_id: i64) -> anyhow::Result<Deco> {
-    let deco = sqlx::query_as!(Deco, "SELECT * FROM deco WHERE id = $1", deco_id)
-        .fetch_one(tx)
-        .await?;
+pub async fn get_deco(tx: &mut PgConnection, deco_id: i64) -> anyhow::Result<Option<Deco>> {
+    let deco = sqlx::query_as!(
+        Deco,
+        r#"
+        SELECT
+            id, created_at, updated_at, name, asset_key, category, is_valid, display_name, width,
+            depth, height
+        FROM deco WHERE id = $1
+        "#,
+        deco_id
+    )
+    .fetch_optional(tx)
+    .await?;
 
     Ok(deco)
 }
@@ -28,19 +43,31 @@ pub struct CreateDecoParams {
     pub name: String,
     pub display_name: Option<String>,
     pub category: Option<String>,
-    pub asset_link: String,
+    pub asset_key: String,
     pub is_valid: bool,
+    pub width: i32,
+    pub depth: i32,
+    pub height: i32,
 }
 
 pub async fn create_deco(tx: &mut PgConnection, params: CreateDecoParams) -> anyhow::Result<Deco> {
     let deco = sqlx::query_as!(
         Deco,
-        "INSERT INTO deco (name, display_name, category, asset_link, is_valid) VALUES ($1, $2, $3, $4, $5) RETURNING *",
+        r#"
+        INSERT INTO deco (name, display_name, category, asset_key, is_valid, width, depth, height)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING
+            id, created_at, updated_at, name, asset_key, category, is_valid, display_name, width,
+            depth, height
+        "#,
         params.name,
         params.display_name,
         params.category,
-        params.asset_link,
-        params.is_valid
+        params.asset_key,
+        params.is_valid,
+        params.width,
+        params.depth,
+        params.height
     )
     .fetch_one(tx)
     .await?;
@@ -48,9 +75,17 @@ pub async fn create_deco(tx: &mut PgConnection, params: CreateDecoParams) -> any
 }
 
 pub async fn get_available_decos(tx: &mut PgConnection) -> anyhow::Result<Vec<Deco>> {
-    let decos = sqlx::query_as!(Deco, "SELECT * FROM deco WHERE is_valid = true")
-        .fetch_all(tx)
-        .await?;
+    let decos = sqlx::query_as!(
+        Deco,
+        r#"
+        SELECT
+            id, created_at, updated_at, name, asset_key, category, is_valid, display_name, width,
+            depth, height
+        FROM deco WHERE is_valid = true
+        "#
+    )
+    .fetch_all(tx)
+    .await?;
 
     Ok(decos)
 }
@@ -1,24 +1,84 @@
 use serde::{Deserialize, Serialize};
-use sqlx::{types::Uuid, FromRow, PgConnection, PgPool, Postgres};
+use serde_json::json;
+use sqlx::{
+    types::{Json, Uuid},
+    FromRow, PgConnection, PgPool, Postgres,
+};
 use time::{Date, OffsetDateTime};
 
+use crate::{
+    analysis::emotion::{Emotion, EmotionAnalysis, EmotionScores},
+    audio::probe::AudioInfo,
+};
+
+// postgres channel carrying `DiaryStatusEvent`s
+pub const DIARY_STATUS_CHANNEL: &str = "diary_status";
+
+#[derive(sqlx::Type, Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "diary_processing_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingStatus {
+    Uploaded,
+    Transcribing,
+    Summarizing,
+    Complete,
+    Failed,
+}
+
+impl ProcessingStatus {
+    pub fn is_terminal(self) -> bool {
+        matches!(self, ProcessingStatus::Complete | ProcessingStatus::Failed)
+    }
+
+    // job retries may re-enter the state they were in, and an edited
+    // transcription sends a finished diary back to summarizing
+    pub fn can_transition_to(self, next: ProcessingStatus) -> bool {
+        use ProcessingStatus::*;
+        matches!(
+            (self, next),
+            (Uploaded | Transcribing, Transcribing)
+                | (Transcribing | Summarizing | Complete | Failed, Summarizing)
+                | (Summarizing, Complete)
+                | (Uploaded | Transcribing | Summarizing, Failed)
+        )
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct DiaryStatusEvent {
+    pub diary_id: i64,
+    pub user_id: Uuid,
+    pub processing_status: ProcessingStatus,
+    pub processing_error: Option<String>,
+}
+
 #[derive(Deserialize, Serialize, Clone, FromRow, Debug)]
 pub struct Diary {
-    id: i64,
+    pub id: i64,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
-    local_date: Date,
-    user_id: Uuid,
-    audio_link: Option<String>,
-    summary: Option<String>,
-    transcription: Option<String>,
-    emotion: Option<String>,
+    pub local_date: Date,
+    pub user_id: Uuid,
+    // signed into `audio_link` when served
+    #[serde(skip)]
+    pub audio_key: Option<String>,
+    pub duration_ms: Option<i32>,
+    sample_rate: Option<i32>,
+    codec: Option<String>,
+    pub summary: Option<String>,
+    pub transcription: Option<String>,
+    pub emotion: Option<Emotion>,
+    emotion_scores: Option<Json<EmotionScores>>,
     is_private: bool,
+    pub processing_status: ProcessingStatus,
+    pub processing_error: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub deleted_at: Option<OffsetDateTime>,
 }
 
 pub struct DiaryParams {
     user_id: Uuid,
-    audio_link: Option<String>,
+    audio_key: Option<String>,
     summary: Option<String>,
     is_private: bool,
 }
@@ -26,13 +86,13 @@ pub struct DiaryParams {
 impl DiaryParams {
     pub fn new(
         user_id: Uuid,
-        audio_link: Option<String>,
+        audio_key: Option<String>,
         summary: Option<String>,
         is_private: bool,
     ) -> Self {
         Self {
             user_id,
-            audio_link,
+            audio_key,
             summary,
             is_private,
         }
@@ -46,7 +106,15 @@ pub async fn get_diaries(
 ) -> anyhow::Result<Vec<Diary>> {
     let resp: Vec<Diary> = sqlx::query_as!(
         Diary,
-        "SELECT * FROM diary WHERE user_id = $1 AND id = ANY($2)",
+        r#"
+        SELECT
+            id, created_at, local_date, user_id, audio_key, duration_ms, sample_rate, codec, summary,
+            transcription,
+            emotion as "emotion: Emotion", emotion_scores as "emotion_scores: Json<EmotionScores>",
+            is_private, processing_status as "processing_status: ProcessingStatus", processing_error,
+            deleted_at
+        FROM diary WHERE user_id = $1 AND id = ANY($2) AND deleted_at IS NULL
+        "#,
         user_id,
         &diary_ids
     )
@@ -55,6 +123,52 @@ pub async fn get_diaries(
     Ok(resp)
 }
 
+// internal lookup for background jobs, not scoped to a user
+pub async fn get_diary_by_id(tx: &mut PgConnection, id: i64) -> anyhow::Result<Option<Diary>> {
+    let resp = sqlx::query_as!(
+        Diary,
+        r#"
+        SELECT
+            id, created_at, local_date, user_id, audio_key, duration_ms, sample_rate, codec, summary,
+            transcription,
+            emotion as "emotion: Emotion", emotion_scores as "emotion_scores: Json<EmotionScores>",
+            is_private, processing_status as "processing_status: ProcessingStatus", processing_error,
+            deleted_at
+        FROM diary WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(tx)
+    .await?;
+    Ok(resp)
+}
+
+// locks the caller's diary for the rest of the transaction
+pub async fn get_user_diary_for_update(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    id: i64,
+) -> anyhow::Result<Option<Diary>> {
+    let resp = sqlx::query_as!(
+        Diary,
+        r#"
+        SELECT
+            id, created_at, local_date, user_id, audio_key, duration_ms, sample_rate, codec, summary,
+            transcription,
+            emotion as "emotion: Emotion", emotion_scores as "emotion_scores: Json<EmotionScores>",
+            is_private, processing_status as "processing_status: ProcessingStatus", processing_error,
+            deleted_at
+        FROM diary WHERE user_id = $1 AND id = $2 AND deleted_at IS NULL
+        FOR UPDATE
+        "#,
+        user_id,
+        id
+    )
+    .fetch_optional(tx)
+    .await?;
+    Ok(resp)
+}
+
 pub async fn get_diaries_of_month(
     pool: &PgPool,
     year: u32,
@@ -64,10 +178,19 @@ pub async fn get_diaries_of_month(
     // todo: connect to supabase and get diaries of user
     let resp: Vec<Diary> = sqlx::query_as!(
         Diary,
-        "SELECT * FROM diary where 
-    EXTRACT(YEAR from created_at) = $1 AND
-    EXTRACT(MONTH from created_at) = $2 AND
-    user_id = $3",
+        r#"
+        SELECT
+            id, created_at, local_date, user_id, audio_key, duration_ms, sample_rate, codec, summary,
+            transcription,
+            emotion as "emotion: Emotion", emotion_scores as "emotion_scores: Json<EmotionScores>",
+            is_private, processing_status as "processing_status: ProcessingStatus", processing_error,
+            deleted_at
+        FROM diary where
+        EXTRACT(YEAR from created_at) = $1 AND
+        EXTRACT(MONTH from created_at) = $2 AND
+        user_id = $3 AND
+        deleted_at IS NULL
+        "#,
         year as i32,
         month as i32,
         user_id
@@ -77,12 +200,41 @@ pub async fn get_diaries_of_month(
     Ok(resp)
 }
 
+// the user's diaries with a local date in `[from, to)`, oldest first
+pub async fn get_diaries_between(
+    pool: &PgPool,
+    user_id: Uuid,
+    from: Date,
+    to: Date,
+) -> anyhow::Result<Vec<Diary>> {
+    let resp = sqlx::query_as!(
+        Diary,
+        r#"
+        SELECT
+            id, created_at, local_date, user_id, audio_key, duration_ms, sample_rate, codec, summary,
+            transcription,
+            emotion as "emotion: Emotion", emotion_scores as "emotion_scores: Json<EmotionScores>",
+            is_private, processing_status as "processing_status: ProcessingStatus", processing_error,
+            deleted_at
+        FROM diary
+        WHERE user_id = $1 AND local_date >= $2 AND local_date < $3 AND deleted_at IS NULL
+        ORDER BY local_date, created_at, id
+        "#,
+        user_id,
+        from,
+        to
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(resp)
+}
+
 pub async fn insert_diary(tx: &mut PgConnection, diary: DiaryParams) -> anyhow::Result<i64> {
     let row = sqlx::query!(
         "
-    INSERT INTO diary (user_id, audio_link, summary, is_private) VALUES ($1,$2,$3,$4) RETURNING id",
+    INSERT INTO diary (user_id, audio_key, summary, is_private) VALUES ($1,$2,$3,$4) RETURNING id",
         diary.user_id,
-        diary.audio_link,
+        diary.audio_key,
         diary.summary,
         diary.is_private
     )
@@ -91,16 +243,35 @@ pub async fn insert_diary(tx: &mut PgConnection, diary: DiaryParams) -> anyhow::
     Ok(row.id)
 }
 
+pub async fn set_diary_audio(
+    tx: &mut PgConnection,
+    id: i64,
+    audio_key: &str,
+    info: Option<&AudioInfo>,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        "UPDATE diary SET audio_key = $1, duration_ms = $2, sample_rate = $3, codec = $4 WHERE id = $5",
+        audio_key,
+        info.map(|info| info.duration_ms),
+        info.map(|info| info.sample_rate),
+        info.map(|info| info.codec.as_str()),
+        id
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
 pub async fn update_diary(
     tx: &mut PgConnection,
     id: i64,
-    audio_link: Option<String>,
+    audio_key: Option<String>,
     summary: Option<String>,
     transcription: Option<String>,
-    emotion: Option<String>,
+    emotion: Option<EmotionAnalysis>,
     is_private: Option<bool>,
 ) -> anyhow::Result<()> {
-    if audio_link.is_none()
+    if audio_key.is_none()
         && summary.is_none()
         && transcription.is_none()
         && is_private.is_none()
@@ -113,12 +284,12 @@ pub async fn update_diary(
     let mut separated = qry_builder.separated(", ");
     let mut first = true;
 
-    if let Some(audio_link) = audio_link {
+    if let Some(audio_key) = audio_key {
         if !first {
             separated.push_unseparated(", ");
         }
-        separated.push_unseparated("audio_link = ");
-        separated.push_bind_unseparated(audio_link);
+        separated.push_unseparated("audio_key = ");
+        separated.push_bind_unseparated(audio_key);
         first = false;
     }
     if let Some(summary) = summary {
@@ -142,7 +313,9 @@ pub async fn update_diary(
             separated.push_unseparated(", ");
         }
         separated.push_unseparated("emotion = ");
-        separated.push_bind_unseparated(emotion);
+        separated.push_bind_unseparated(emotion.emotion);
+        separated.push_unseparated(", emotion_scores = ");
+        separated.push_bind_unseparated(Json(emotion.scores));
         first = false;
     }
     if let Some(is_private) = is_private {
@@ -157,3 +330,219 @@ pub async fn update_diary(
     qry_builder.build().execute(tx).await?;
     Ok(())
 }
+
+// moves the diary to the trash; it is purged after the retention window
+pub async fn soft_delete_diary(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    id: i64,
+) -> anyhow::Result<bool> {
+    let res = sqlx::query!(
+        "UPDATE diary SET deleted_at = now() WHERE user_id = $1 AND id = $2 AND deleted_at IS NULL",
+        user_id,
+        id
+    )
+    .execute(tx)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+pub async fn restore_diary(tx: &mut PgConnection, user_id: Uuid, id: i64) -> anyhow::Result<bool> {
+    let res = sqlx::query!(
+        "UPDATE diary SET deleted_at = NULL WHERE user_id = $1 AND id = $2 AND deleted_at IS NOT NULL",
+        user_id,
+        id
+    )
+    .execute(tx)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+pub async fn get_trashed_diaries(pool: &PgPool, user_id: Uuid) -> anyhow::Result<Vec<Diary>> {
+    let resp = sqlx::query_as!(
+        Diary,
+        r#"
+        SELECT
+            id, created_at, local_date, user_id, audio_key, duration_ms, sample_rate, codec, summary,
+            transcription,
+            emotion as "emotion: Emotion", emotion_scores as "emotion_scores: Json<EmotionScores>",
+            is_private, processing_status as "processing_status: ProcessingStatus", processing_error,
+            deleted_at
+        FROM diary WHERE user_id = $1 AND deleted_at IS NOT NULL
+        ORDER BY deleted_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(resp)
+}
+
+/// Filters of a diary search; every given filter must hold.
+pub struct DiarySearch {
+    // each must appear in the summary or the transcription, case-insensitively
+    pub terms: Vec<String>,
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+    pub emotion: Option<Emotion>,
+    pub is_private: Option<bool>,
+    // `(created_at, id)` of the last diary of the previous page
+    pub after: Option<(OffsetDateTime, i64)>,
+    pub limit: i64,
+}
+
+// newest first, so a cursor stays valid while new diaries come in
+pub async fn search_diaries(
+    pool: &PgPool,
+    user_id: Uuid,
+    search: &DiarySearch,
+) -> anyhow::Result<Vec<Diary>> {
+    let mut qry_builder: sqlx::QueryBuilder<'_, Postgres> = sqlx::QueryBuilder::new(
+        r#"
+        SELECT
+            id, created_at, local_date, user_id, audio_key, duration_ms, sample_rate, codec, summary,
+            transcription, emotion, emotion_scores, is_private, processing_status, processing_error,
+            deleted_at
+        FROM diary WHERE deleted_at IS NULL AND user_id = "#,
+    );
+    qry_builder.push_bind(user_id);
+    for term in &search.terms {
+        // the expression of `diary_search_trgm_idx`
+        qry_builder
+            .push(" AND (coalesce(summary, '') || ' ' || coalesce(transcription, '')) ILIKE ");
+        qry_builder.push_bind(format!("%{}%", escape_like(term)));
+    }
+    if let Some(from) = search.from {
+        qry_builder.push(" AND local_date >= ");
+        qry_builder.push_bind(from);
+    }
+    if let Some(to) = search.to {
+        qry_builder.push(" AND local_date <= ");
+        qry_builder.push_bind(to);
+    }
+    if let Some(emotion) = search.emotion {
+        qry_builder.push(" AND emotion = ");
+        qry_builder.push_bind(emotion);
+    }
+    if let Some(is_private) = search.is_private {
+        qry_builder.push(" AND is_private = ");
+        qry_builder.push_bind(is_private);
+    }
+    if let Some((created_at, id)) = search.after {
+        qry_builder.push(" AND (created_at, id) < (");
+        qry_builder.push_bind(created_at);
+        qry_builder.push(", ");
+        qry_builder.push_bind(id);
+        qry_builder.push(")");
+    }
+    qry_builder.push(" ORDER BY created_at DESC, id DESC LIMIT ");
+    qry_builder.push_bind(search.limit);
+
+    let resp = qry_builder
+        .build_query_as::<Diary>()
+        .fetch_all(pool)
+        .await?;
+    Ok(resp)
+}
+
+// backslash is the default escape character of LIKE
+fn escape_like(term: &str) -> String {
+    term.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+pub struct ExpiredDiary {
+    pub id: i64,
+    pub user_id: Uuid,
+}
+
+pub async fn get_expired_trashed_diaries(
+    pool: &PgPool,
+    retention_secs: f64,
+    limit: i64,
+) -> anyhow::Result<Vec<ExpiredDiary>> {
+    let resp = sqlx::query_as!(
+        ExpiredDiary,
+        "
+        SELECT id, user_id FROM diary
+        WHERE deleted_at < now() - make_interval(secs => $1)
+        ORDER BY deleted_at
+        LIMIT $2
+        ",
+        retention_secs,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(resp)
+}
+
+// permanent removal, only for diaries that are in the trash; `None` if the
+// diary is not in the trash, otherwise the key of its recording, if it had one
+pub async fn delete_trashed_diary(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    id: i64,
+) -> anyhow::Result<Option<Option<String>>> {
+    let row = sqlx::query!(
+        "DELETE FROM diary WHERE user_id = $1 AND id = $2 AND deleted_at IS NOT NULL RETURNING audio_key",
+        user_id,
+        id
+    )
+    .fetch_optional(tx)
+    .await?;
+    Ok(row.map(|row| row.audio_key))
+}
+
+/// Moves the diary to `status` if the state machine allows it and notifies
+/// listeners on commit. Returns whether the transition was applied.
+pub async fn set_processing_status(
+    tx: &mut PgConnection,
+    id: i64,
+    status: ProcessingStatus,
+    error: Option<String>,
+) -> anyhow::Result<bool> {
+    let Some(current) = sqlx::query!(
+        r#"SELECT user_id, processing_status as "processing_status: ProcessingStatus" FROM diary WHERE id = $1 FOR UPDATE"#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(false);
+    };
+    if !current.processing_status.can_transition_to(status) {
+        tracing::warn!(
+            "Ignoring diary {} status transition {:?} -> {:?}",
+            id,
+            current.processing_status,
+            status
+        );
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        "UPDATE diary SET processing_status = $1, processing_error = $2 WHERE id = $3",
+        status as ProcessingStatus,
+        error,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let event = DiaryStatusEvent {
+        diary_id: id,
+        user_id: current.user_id,
+        processing_status: status,
+        processing_error: error,
+    };
+    sqlx::query!(
+        "SELECT pg_notify($1, $2)",
+        DIARY_STATUS_CHANNEL,
+        json!(event).to_string()
+    )
+    .execute(&mut *tx)
+    .await?;
+    Ok(true)
+}
@@ -0,0 +1,72 @@
+use sqlx::{PgConnection, PgPool};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub struct DiaryEmbedding {
+    pub diary_id: i64,
+    pub model: String,
+    pub embedding: Vec<f32>,
+}
+
+pub async fn upsert_diary_embedding(
+    tx: &mut PgConnection,
+    diary_id: i64,
+    model: &str,
+    embedding: &[f32],
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO diary_embedding (diary_id, model, embedding) VALUES ($1, $2, $3)
+        ON CONFLICT (diary_id) DO UPDATE
+        SET model = EXCLUDED.model, embedding = EXCLUDED.embedding, updated_at = now()
+        "#,
+        diary_id,
+        model,
+        embedding as &[f32],
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+// the caller's embeddings of `model` for diaries not in the trash, optionally
+// only those recorded before `before` and leaving out private diaries
+pub async fn get_user_embeddings(
+    pool: &PgPool,
+    user_id: Uuid,
+    model: &str,
+    before: Option<OffsetDateTime>,
+    include_private: bool,
+) -> anyhow::Result<Vec<DiaryEmbedding>> {
+    let resp = sqlx::query_as!(
+        DiaryEmbedding,
+        r#"
+        SELECT e.diary_id, e.model, e.embedding
+        FROM diary_embedding e JOIN diary d ON d.id = e.diary_id
+        WHERE d.user_id = $1 AND d.deleted_at IS NULL AND e.model = $2
+            AND ($3::timestamptz IS NULL OR d.created_at < $3)
+            AND ($4 OR NOT d.is_private)
+        "#,
+        user_id,
+        model,
+        before,
+        include_private,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(resp)
+}
+
+pub async fn get_diary_embedding(
+    pool: &PgPool,
+    diary_id: i64,
+) -> anyhow::Result<Option<DiaryEmbedding>> {
+    let resp = sqlx::query_as!(
+        DiaryEmbedding,
+        "SELECT diary_id, model, embedding FROM diary_embedding WHERE diary_id = $1",
+        diary_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(resp)
+}
@@ -0,0 +1,59 @@
+use sqlx::{PgConnection, PgPool};
+
+use crate::transcription::transcript::TranscriptSegment;
+
+/// Replaces the stored segments of a diary with a fresh transcription's.
+pub async fn replace_diary_segments(
+    tx: &mut PgConnection,
+    diary_id: i64,
+    segments: &[TranscriptSegment],
+) -> sqlx::Result<()> {
+    sqlx::query!("DELETE FROM diary_segment WHERE diary_id = $1", diary_id)
+        .execute(&mut *tx)
+        .await?;
+    if segments.is_empty() {
+        return Ok(());
+    }
+
+    let idx = (0..segments.len() as i32).collect::<Vec<_>>();
+    let start_ms = segments.iter().map(|s| s.start_ms).collect::<Vec<_>>();
+    let end_ms = segments.iter().map(|s| s.end_ms).collect::<Vec<_>>();
+    let text = segments.iter().map(|s| s.text.clone()).collect::<Vec<_>>();
+    let speaker = segments
+        .iter()
+        .map(|s| s.speaker.clone())
+        .collect::<Vec<_>>();
+    sqlx::query!(
+        r#"
+        INSERT INTO diary_segment (diary_id, idx, start_ms, end_ms, text, speaker)
+        SELECT $1, * FROM UNNEST($2::int4[], $3::int4[], $4::int4[], $5::text[], $6::text[])
+        "#,
+        diary_id,
+        &idx,
+        &start_ms,
+        &end_ms,
+        &text,
+        &speaker as &[Option<String>],
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_diary_segments(
+    tx: &PgPool,
+    diary_id: i64,
+) -> anyhow::Result<Vec<TranscriptSegment>> {
+    let resp = sqlx::query_as!(
+        TranscriptSegment,
+        r#"
+        SELECT start_ms, end_ms, text, speaker
+        FROM diary_segment WHERE diary_id = $1
+        ORDER BY idx
+        "#,
+        diary_id
+    )
+    .fetch_all(tx)
+    .await?;
+    Ok(resp)
+}
@@ -0,0 +1,147 @@
+use serde_json::json;
+use sqlx::{types::Json, FromRow, PgConnection, PgPool};
+use time::OffsetDateTime;
+
+use crate::jobs::kind::Job;
+
+#[derive(sqlx::Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "job_status", rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Dead,
+}
+
+#[derive(Clone, FromRow, Debug)]
+pub struct JobRow {
+    pub id: i64,
+    pub kind: String,
+    pub payload: Json<Job>,
+    pub status: JobStatus,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: OffsetDateTime,
+    pub locked_at: Option<OffsetDateTime>,
+    pub locked_by: Option<String>,
+    pub last_error: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+pub async fn enqueue_job(tx: &mut PgConnection, job: Job) -> sqlx::Result<i64> {
+    let row = sqlx::query!(
+        "INSERT INTO job (kind, payload, max_attempts) VALUES ($1, $2, $3) RETURNING id",
+        job.kind(),
+        json!(job),
+        job.max_attempts(),
+    )
+    .fetch_one(tx)
+    .await?;
+    Ok(row.id)
+}
+
+/// Claims the next runnable job: a queued job that is due, or a running job whose
+/// worker has not reported back within `lease_secs` (i.e. it crashed or was killed).
+pub async fn claim_job(
+    pool: &PgPool,
+    worker_id: &str,
+    lease_secs: f64,
+) -> sqlx::Result<Option<JobRow>> {
+    sqlx::query_as!(
+        JobRow,
+        r#"
+        UPDATE job
+        SET status = 'running', attempts = attempts + 1, locked_at = now(), locked_by = $1, updated_at = now()
+        WHERE id = (
+            SELECT id FROM job
+            WHERE (status = 'queued' AND run_at <= now())
+                OR (status = 'running' AND locked_at < now() - make_interval(secs => $2))
+            ORDER BY run_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING
+            id, kind, payload as "payload: Json<Job>", status as "status: JobStatus",
+            attempts, max_attempts, run_at, locked_at, locked_by, last_error, created_at, updated_at
+        "#,
+        worker_id,
+        lease_secs,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Renews the lease of a job the worker is still running. Returns false once
+/// the job is no longer the worker's, i.e. it was reclaimed by another one.
+pub async fn extend_job_lease(pool: &PgPool, id: i64, worker_id: &str) -> sqlx::Result<bool> {
+    let result = sqlx::query!(
+        "
+        UPDATE job SET locked_at = now(), updated_at = now()
+        WHERE id = $1 AND locked_by = $2 AND status = 'running'
+        ",
+        id,
+        worker_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn complete_job(pool: &PgPool, id: i64, worker_id: &str) -> sqlx::Result<()> {
+    sqlx::query!(
+        "
+        UPDATE job SET status = 'done', locked_at = NULL, last_error = NULL, updated_at = now()
+        WHERE id = $1 AND locked_by = $2
+        ",
+        id,
+        worker_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn retry_job(
+    pool: &PgPool,
+    id: i64,
+    worker_id: &str,
+    error: &str,
+    backoff_secs: f64,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        "
+        UPDATE job
+        SET status = 'queued', locked_at = NULL, last_error = $3,
+            run_at = now() + make_interval(secs => $4), updated_at = now()
+        WHERE id = $1 AND locked_by = $2
+        ",
+        id,
+        worker_id,
+        error,
+        backoff_secs,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn dead_letter_job(
+    pool: &PgPool,
+    id: i64,
+    worker_id: &str,
+    error: &str,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        "
+        UPDATE job SET status = 'dead', locked_at = NULL, last_error = $3, updated_at = now()
+        WHERE id = $1 AND locked_by = $2
+        ",
+        id,
+        worker_id,
+        error,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
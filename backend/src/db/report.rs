@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, PgConnection, PgPool};
+use time::{Date, Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::analysis::{emotion::Emotion, report::ReflectionDigest};
+
+#[derive(sqlx::Type, Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "report_period", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ReportPeriod {
+    // monday to sunday
+    Week,
+    Month,
+}
+
+impl ReportPeriod {
+    /// The first day after the period starting at `start`.
+    pub fn end(self, start: Date) -> Date {
+        match self {
+            ReportPeriod::Week => start + Duration::days(7),
+            // some day of the next month, moved to its first
+            ReportPeriod::Month => (self.start_of(start) + Duration::days(31))
+                .replace_day(1)
+                .expect("every month has a first day"),
+        }
+    }
+
+    /// Start of the period before the one starting at `start`.
+    pub fn previous(self, start: Date) -> Date {
+        self.start_of(start - Duration::days(1))
+    }
+
+    /// Start of the period containing `date`.
+    pub fn start_of(self, date: Date) -> Date {
+        match self {
+            ReportPeriod::Week => {
+                date - Duration::days(date.weekday().number_days_from_monday() as i64)
+            }
+            ReportPeriod::Month => date.replace_day(1).expect("every month has a first day"),
+        }
+    }
+}
+
+pub type EmotionCounts = BTreeMap<Emotion, i32>;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Report {
+    pub period: ReportPeriod,
+    pub period_start: Date,
+    // last day of the period
+    pub period_end: Date,
+    pub diary_count: i32,
+    pub emotion_counts: Json<EmotionCounts>,
+    pub digest: Json<ReflectionDigest>,
+    #[serde(skip)]
+    pub fingerprint: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+pub async fn get_report(
+    pool: &PgPool,
+    user_id: Uuid,
+    period: ReportPeriod,
+    period_start: Date,
+) -> anyhow::Result<Option<Report>> {
+    let resp = sqlx::query_as!(
+        Report,
+        r#"
+        SELECT
+            period as "period: ReportPeriod", period_start, period_end, diary_count,
+            emotion_counts as "emotion_counts: Json<EmotionCounts>",
+            digest as "digest: Json<ReflectionDigest>", fingerprint, updated_at
+        FROM report WHERE user_id = $1 AND period = $2 AND period_start = $3
+        "#,
+        user_id,
+        period as ReportPeriod,
+        period_start
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(resp)
+}
+
+pub struct ReportParams {
+    pub period: ReportPeriod,
+    pub period_start: Date,
+    pub diary_count: i32,
+    pub emotion_counts: EmotionCounts,
+    pub digest: ReflectionDigest,
+    pub fingerprint: String,
+}
+
+pub async fn upsert_report(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    report: ReportParams,
+) -> anyhow::Result<Report> {
+    let period_end = report.period.end(report.period_start) - Duration::days(1);
+    let resp = sqlx::query_as!(
+        Report,
+        r#"
+        INSERT INTO report (
+            user_id, period, period_start, period_end, diary_count, emotion_counts, digest,
+            fingerprint
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (user_id, period, period_start) DO UPDATE
+        SET diary_count = EXCLUDED.diary_count, emotion_counts = EXCLUDED.emotion_counts,
+            digest = EXCLUDED.digest, fingerprint = EXCLUDED.fingerprint, updated_at = now()
+        RETURNING
+            period as "period: ReportPeriod", period_start, period_end, diary_count,
+            emotion_counts as "emotion_counts: Json<EmotionCounts>",
+            digest as "digest: Json<ReflectionDigest>", fingerprint, updated_at
+        "#,
+        user_id,
+        report.period as ReportPeriod,
+        report.period_start,
+        period_end,
+        report.diary_count,
+        Json(report.emotion_counts) as _,
+        Json(report.digest) as _,
+        report.fingerprint,
+    )
+    .fetch_one(tx)
+    .await?;
+    Ok(resp)
+}
+
+/// Users with summarized diaries in `[start, end)` but no report of it, and no
+/// job generating one either (a dead job is not retried by the scheduler).
+pub async fn get_users_due_report(
+    pool: &PgPool,
+    period: ReportPeriod,
+    start: Date,
+    end: Date,
+) -> anyhow::Result<Vec<Uuid>> {
+    let resp = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT d.user_id FROM diary d
+        WHERE d.local_date >= $2 AND d.local_date < $3
+            AND d.deleted_at IS NULL AND d.summary IS NOT NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM report r
+                WHERE r.user_id = d.user_id AND r.period = $1 AND r.period_start = $2
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM job j
+                WHERE j.kind = 'generate_report' AND j.payload @> jsonb_build_object(
+                    'user_id', d.user_id, 'period', $1::report_period, 'period_start', $2::date
+                )
+            )
+        "#,
+        period as ReportPeriod,
+        start,
+        end,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(resp)
+}
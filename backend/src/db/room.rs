@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{types::Json, PgConnection, PgPool};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::room::layout::LayoutPlacement;
+
+/// Version of the layout of a room, 0 if it was never changed.
+pub async fn get_layout_version(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    year: i32,
+    month: u32,
+) -> sqlx::Result<i64> {
+    let version = sqlx::query_scalar!(
+        "SELECT layout_version FROM room WHERE user_id = $1 AND year = $2 AND month = $3",
+        user_id,
+        year,
+        month as i32,
+    )
+    .fetch_optional(tx)
+    .await?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Catalog decos giving a room its look, unset ones use the app's defaults.
+#[derive(Deserialize, Serialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct RoomTheme {
+    pub wallpaper_id: Option<i64>,
+    pub floor_id: Option<i64>,
+    pub lighting_id: Option<i64>,
+    pub size_id: Option<i64>,
+}
+
+pub async fn get_room_theme(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    year: i32,
+    month: u32,
+) -> sqlx::Result<RoomTheme> {
+    let theme = sqlx::query_as!(
+        RoomTheme,
+        "
+        SELECT wallpaper_id, floor_id, lighting_id, size_id
+        FROM room WHERE user_id = $1 AND year = $2 AND month = $3
+        ",
+        user_id,
+        year,
+        month as i32,
+    )
+    .fetch_optional(tx)
+    .await?;
+    Ok(theme.unwrap_or_default())
+}
+
+/// Sets the theme of a room locked by `lock_room`.
+pub async fn update_room_theme(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    year: i32,
+    month: u32,
+    theme: RoomTheme,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        "
+        UPDATE room
+        SET wallpaper_id = $4, floor_id = $5, lighting_id = $6, size_id = $7, updated_at = now()
+        WHERE user_id = $1 AND year = $2 AND month = $3
+        ",
+        user_id,
+        year,
+        month as i32,
+        theme.wallpaper_id,
+        theme.floor_id,
+        theme.lighting_id,
+        theme.size_id,
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+/// Locks the room until the end of the transaction, so layout changes of the
+/// same room run one after the other, and returns its layout version.
+pub async fn lock_room(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    year: i32,
+    month: u32,
+) -> sqlx::Result<i64> {
+    sqlx::query!(
+        "
+        INSERT INTO room (user_id, year, month) VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, year, month) DO NOTHING
+        ",
+        user_id,
+        year,
+        month as i32,
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query_scalar!(
+        "SELECT layout_version FROM room WHERE user_id = $1 AND year = $2 AND month = $3 FOR UPDATE",
+        user_id,
+        year,
+        month as i32,
+    )
+    .fetch_one(tx)
+    .await
+}
+
+pub async fn bump_layout_version(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    year: i32,
+    month: u32,
+) -> sqlx::Result<i64> {
+    sqlx::query_scalar!(
+        "
+        UPDATE room SET layout_version = layout_version + 1, updated_at = now()
+        WHERE user_id = $1 AND year = $2 AND month = $3
+        RETURNING layout_version
+        ",
+        user_id,
+        year,
+        month as i32,
+    )
+    .fetch_one(tx)
+    .await
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LayoutSnapshotSummary {
+    pub version: i64,
+    // decos placed in it
+    pub placed: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Clone, Debug)]
+pub struct LayoutSnapshot {
+    pub version: i64,
+    pub placements: Json<Vec<LayoutPlacement>>,
+    pub created_at: OffsetDateTime,
+}
+
+/// Records the placements of a layout version, once.
+pub async fn insert_layout_snapshot(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    year: i32,
+    month: u32,
+    version: i64,
+    placements: &[LayoutPlacement],
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        "
+        INSERT INTO room_layout_snapshot (user_id, year, month, version, placements)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (user_id, year, month, version) DO NOTHING
+        ",
+        user_id,
+        year,
+        month as i32,
+        version,
+        json!(placements),
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+/// Keeps the user's `keep` most recent snapshots, across all rooms.
+pub async fn prune_layout_snapshots(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    keep: i64,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        "
+        DELETE FROM room_layout_snapshot
+        WHERE user_id = $1 AND (year, month, version) NOT IN (
+            SELECT year, month, version FROM room_layout_snapshot
+            WHERE user_id = $1
+            ORDER BY created_at DESC, version DESC
+            LIMIT $2
+        )
+        ",
+        user_id,
+        keep,
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+/// Snapshots of a room, newest first.
+pub async fn get_layout_snapshots(
+    pool: &PgPool,
+    user_id: Uuid,
+    year: i32,
+    month: u32,
+) -> sqlx::Result<Vec<LayoutSnapshotSummary>> {
+    sqlx::query_as!(
+        LayoutSnapshotSummary,
+        r#"
+        SELECT version, jsonb_array_length(placements) as "placed!", created_at
+        FROM room_layout_snapshot
+        WHERE user_id = $1 AND year = $2 AND month = $3
+        ORDER BY version DESC
+        "#,
+        user_id,
+        year,
+        month as i32,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_layout_snapshot(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    year: i32,
+    month: u32,
+    version: i64,
+) -> sqlx::Result<Option<LayoutSnapshot>> {
+    sqlx::query_as!(
+        LayoutSnapshot,
+        r#"
+        SELECT version, placements as "placements: Json<Vec<LayoutPlacement>>", created_at
+        FROM room_layout_snapshot
+        WHERE user_id = $1 AND year = $2 AND month = $3 AND version = $4
+        "#,
+        user_id,
+        year,
+        month as i32,
+        version,
+    )
+    .fetch_optional(tx)
+    .await
+}
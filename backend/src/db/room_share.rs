@@ -0,0 +1,104 @@
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RoomShare {
+    pub id: i64,
+    #[serde(skip)]
+    pub user_id: Uuid,
+    pub year: i32,
+    pub month: i32,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+pub async fn create_room_share(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    year: i32,
+    month: u32,
+    token_hash: &str,
+    expires_at: Option<OffsetDateTime>,
+) -> sqlx::Result<RoomShare> {
+    sqlx::query_as!(
+        RoomShare,
+        "
+        INSERT INTO room_share (user_id, year, month, token_hash, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, user_id, year, month, expires_at, created_at
+        ",
+        user_id,
+        year,
+        month as i32,
+        token_hash,
+        expires_at,
+    )
+    .fetch_one(tx)
+    .await
+}
+
+/// The user's links to a room that still work, newest first.
+pub async fn get_room_shares(
+    pool: &PgPool,
+    user_id: Uuid,
+    year: i32,
+    month: u32,
+) -> sqlx::Result<Vec<RoomShare>> {
+    sqlx::query_as!(
+        RoomShare,
+        "
+        SELECT id, user_id, year, month, expires_at, created_at
+        FROM room_share
+        WHERE user_id = $1 AND year = $2 AND month = $3
+            AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > now())
+        ORDER BY created_at DESC
+        ",
+        user_id,
+        year,
+        month as i32,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// The share a token opens, unless it was revoked or has expired.
+pub async fn get_active_room_share(
+    tx: &mut PgConnection,
+    token_hash: &str,
+) -> sqlx::Result<Option<RoomShare>> {
+    sqlx::query_as!(
+        RoomShare,
+        "
+        SELECT id, user_id, year, month, expires_at, created_at
+        FROM room_share
+        WHERE token_hash = $1
+            AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > now())
+        ",
+        token_hash,
+    )
+    .fetch_optional(tx)
+    .await
+}
+
+/// Returns false when the user has no such share, or it was already revoked.
+pub async fn revoke_room_share(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    share_id: i64,
+) -> sqlx::Result<bool> {
+    let result = sqlx::query!(
+        "
+        UPDATE room_share SET revoked_at = now()
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+        ",
+        share_id,
+        user_id,
+    )
+    .execute(tx)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -7,27 +7,49 @@ use sqlx::{
 use time::{Date, OffsetDateTime};
 use uuid::Uuid;
 
-use crate::utils::coordinates::Coordinates;
+use crate::{room::grid::Footprint, utils::coordinates::Coordinates};
 
 #[derive(Deserialize, Serialize, Clone, FromRow, Debug)]
 pub struct UserDeco {
     user_id: Uuid,
     // deco part
-    deco_id: i64,
-    name: String,
-    asset_link: String,
-    category: Option<String>,
-    display_name: Option<String>,
+    pub deco_id: i64,
+    pub name: String,
+    // signed into `asset_link` when served
+    #[serde(skip)]
+    pub asset_key: Option<String>,
+    pub category: Option<String>,
+    pub display_name: Option<String>,
+    pub width: i32,
+    pub depth: i32,
+    pub height: i32,
     // diary part
     diary_id: i64,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
-    local_date: Date,
-    audio_link: Option<String>,
+    pub local_date: Date,
+    // signed into `audio_link` when served
+    #[serde(skip)]
+    pub audio_key: Option<String>,
     summary: Option<String>,
-    is_private: bool,
-    is_placed: bool,
-    coordinates: Option<Json<Coordinates>>,
+    pub is_private: bool,
+    pub is_placed: bool,
+    pub coordinates: Option<Json<Coordinates>>,
+}
+
+impl UserDeco {
+    pub fn key(&self) -> (i64, i64) {
+        (self.diary_id, self.deco_id)
+    }
+
+    /// The same deco at `coordinates`, or out of the room.
+    pub fn placed_at(self, coordinates: Option<Coordinates>) -> Self {
+        Self {
+            is_placed: coordinates.is_some(),
+            coordinates: coordinates.map(Json),
+            ..self
+        }
+    }
 }
 
 pub async fn get_user_deco_of_month(
@@ -43,13 +65,16 @@ pub async fn get_user_deco_of_month(
             diary.user_id as user_id,
             deco.id as deco_id,
             deco.name as name,
-            deco.asset_link as asset_link,
+            deco.asset_key as asset_key,
             deco.category as category,
             deco.display_name as display_name,
+            deco.width as width,
+            deco.depth as depth,
+            deco.height as height,
             diary.id as diary_id,
             diary.created_at as created_at,
             diary.local_date as local_date,
-            diary.audio_link as audio_link,
+            diary.audio_key as audio_key,
             diary.summary as summary,
             diary.is_private as is_private,
             user_deco.is_placed as is_placed,
@@ -58,6 +83,93 @@ pub async fn get_user_deco_of_month(
         JOIN user_deco ON user_deco.user_id = $1 AND user_deco.deco_id = deco.id
         JOIN diary ON diary.id = user_deco.diary_id
         WHERE diary.user_id = $1 AND EXTRACT(YEAR FROM local_date) = $2 AND EXTRACT(MONTH FROM local_date) = $3
+            AND diary.deleted_at IS NULL
+        "#,
+        user_id,
+        BigDecimal::from(year),
+        month as i32,
+    )
+    .fetch_all(tx)
+    .await
+}
+
+/// A user deco with what placement checks need to know about it.
+#[derive(Clone, Debug)]
+pub struct PlacedDeco {
+    pub diary_id: i64,
+    pub deco_id: i64,
+    pub name: String,
+    pub width: i32,
+    pub depth: i32,
+    pub height: i32,
+    pub local_date: Date,
+    pub coordinates: Option<Json<Coordinates>>,
+}
+
+impl PlacedDeco {
+    /// `(year, month)` of the room it belongs to.
+    pub fn room(&self) -> (i32, u32) {
+        (
+            self.local_date.year(),
+            u8::from(self.local_date.month()) as u32,
+        )
+    }
+
+    pub fn footprint(&self) -> Footprint {
+        Footprint {
+            width: self.width as i64,
+            depth: self.depth as i64,
+            height: self.height as i64,
+        }
+    }
+}
+
+pub async fn get_placed_deco(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    diary_id: i64,
+    deco_id: i64,
+) -> sqlx::Result<Option<PlacedDeco>> {
+    sqlx::query_as!(
+        PlacedDeco,
+        r#"
+        SELECT
+            user_deco.diary_id, user_deco.deco_id, deco.name, deco.width, deco.depth, deco.height,
+            diary.local_date, user_deco.coordinates as "coordinates: Json<Coordinates>"
+        FROM user_deco
+        JOIN deco ON deco.id = user_deco.deco_id
+        JOIN diary ON diary.id = user_deco.diary_id
+        WHERE user_deco.user_id = $1 AND user_deco.diary_id = $2 AND user_deco.deco_id = $3
+            AND diary.user_id = $1 AND diary.deleted_at IS NULL
+        "#,
+        user_id,
+        diary_id,
+        deco_id,
+    )
+    .fetch_optional(tx)
+    .await
+}
+
+/// Every user deco in the room of a month, placed or not.
+pub async fn get_room_decos(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    year: i32,
+    month: u32,
+) -> sqlx::Result<Vec<PlacedDeco>> {
+    sqlx::query_as!(
+        PlacedDeco,
+        r#"
+        SELECT
+            user_deco.diary_id, user_deco.deco_id, deco.name, deco.width, deco.depth, deco.height,
+            diary.local_date, user_deco.coordinates as "coordinates: Json<Coordinates>"
+        FROM user_deco
+        JOIN deco ON deco.id = user_deco.deco_id
+        JOIN diary ON diary.id = user_deco.diary_id
+        WHERE user_deco.user_id = $1 AND diary.user_id = $1
+            AND EXTRACT(YEAR FROM diary.local_date) = $2
+            AND EXTRACT(MONTH FROM diary.local_date) = $3
+            AND diary.deleted_at IS NULL
         "#,
         user_id,
         BigDecimal::from(year),
@@ -67,16 +179,19 @@ pub async fn get_user_deco_of_month(
     .await
 }
 
+/// Returns false when the diary is not one of the user's, or is in the trash.
 pub async fn create_user_deco(
     tx: &mut PgConnection,
     user_id: Uuid,
     diary_id: i64,
     deco_id: i64,
-) -> sqlx::Result<()> {
-    sqlx::query!(
+) -> sqlx::Result<bool> {
+    let result = sqlx::query!(
         "
         INSERT INTO user_deco (user_id, diary_id, deco_id)
-        VALUES ($1, $2, $3)
+        SELECT $1, diary.id, $3
+        FROM diary
+        WHERE diary.id = $2 AND diary.user_id = $1 AND diary.deleted_at IS NULL
         ",
         user_id,
         diary_id,
@@ -84,7 +199,7 @@ pub async fn create_user_deco(
     )
     .execute(tx)
     .await?;
-    Ok(())
+    Ok(result.rows_affected() > 0)
 }
 
 pub async fn update_user_deco(
@@ -112,3 +227,18 @@ pub async fn update_user_deco(
     .await?;
     Ok(())
 }
+
+pub async fn delete_user_decos_of_diary(
+    tx: &mut PgConnection,
+    user_id: Uuid,
+    diary_id: i64,
+) -> sqlx::Result<()> {
+    sqlx::query!(
+        "DELETE FROM user_deco WHERE user_id = $1 AND diary_id = $2",
+        user_id,
+        diary_id,
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+
+use crate::openai::embedding::OpenAIEmbedder;
+
+use super::hash::HashEmbedder;
+
+/// Turns text into a vector whose cosine similarity to another text's tracks
+/// how alike they are. Vectors of different models are not comparable, so
+/// they are stored along with `model()`.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    fn model(&self) -> &str;
+    async fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>>;
+}
+
+/// Picks the backend named by `EMBEDDER` (`openai` or `hash`).
+pub fn embedder_from_env() -> anyhow::Result<Arc<dyn Embedder>> {
+    let backend = std::env::var("EMBEDDER").unwrap_or_else(|_| "openai".to_string());
+    match backend.as_str() {
+        "openai" => Ok(Arc::new(OpenAIEmbedder::from_env()?)),
+        "hash" => Ok(Arc::new(HashEmbedder::default())),
+        other => Err(anyhow!("Unknown EMBEDDER backend: {}", other)),
+    }
+}
+
+/// 0 when either vector is zero or their lengths differ.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    (dot / (norm_a.sqrt() * norm_b.sqrt())).clamp(-1.0, 1.0)
+}
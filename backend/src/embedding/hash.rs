@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+
+use super::embedder::Embedder;
+
+const DIMENSIONS: usize = 256;
+
+/// Deterministic bag-of-features embedding for tests and offline
+/// development: words and the character bigrams within them are hashed into
+/// a fixed number of buckets. Texts sharing vocabulary end up close, which is
+/// enough to exercise retrieval without a model.
+pub struct HashEmbedder {
+    dimensions: usize,
+    model: String,
+}
+
+impl Default for HashEmbedder {
+    fn default() -> Self {
+        Self {
+            dimensions: DIMENSIONS,
+            model: format!("hash-{}", DIMENSIONS),
+        }
+    }
+}
+
+// FNV-1a, stable across runs and platforms unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[async_trait]
+impl Embedder for HashEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+        let mut vector = vec![0.0f32; self.dimensions];
+        let mut add = |feature: &str| {
+            let hash = fnv1a(feature.as_bytes());
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[(hash % self.dimensions as u64) as usize] += sign;
+        };
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+        {
+            let word = word.to_lowercase();
+            add(&word);
+            // korean particles stick to words, bigrams still match the stem
+            let chars = word.chars().collect::<Vec<_>>();
+            for pair in chars.windows(2) {
+                add(&pair.iter().collect::<String>());
+            }
+        }
+
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        Ok(vector)
+    }
+}
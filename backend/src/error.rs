@@ -0,0 +1,199 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+use crate::{
+    audio::pipeline::AudioError, auth::jwt::AuthError, utils::request_id::current_request_id,
+};
+
+pub type AppResult<T> = Result<T, AppError>;
+
+/// Error returned by every handler, rendered as `{code, message, request_id}`
+/// plus `details` for the errors that carry some.
+#[derive(Debug)]
+pub enum AppError {
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Validation(String),
+    Conflict(String),
+    // a conflict the client can act on, `details` says with what
+    ConflictWith(String, serde_json::Value),
+    // the uploaded file is not in an accepted format
+    UnsupportedMediaType(String),
+    // a third-party api (e.g. OpenAI) failed
+    Upstream(String),
+    // the object storage failed
+    Storage(String),
+    Database(sqlx::Error),
+    Internal(anyhow::Error),
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code: &'static str,
+    message: String,
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<serde_json::Value>,
+}
+
+impl AppError {
+    pub fn not_found(what: &str) -> Self {
+        AppError::NotFound(format!("{} not found", what))
+    }
+
+    pub fn storage<E: std::fmt::Display>(e: E) -> Self {
+        AppError::Storage(e.to_string())
+    }
+
+    pub fn upstream<E: std::fmt::Display>(e: E) -> Self {
+        AppError::Upstream(e.to_string())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Conflict(_) | AppError::ConflictWith(..) => StatusCode::CONFLICT,
+            AppError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::Upstream(_) | AppError::Storage(_) => StatusCode::BAD_GATEWAY,
+            AppError::Database(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::Validation(_) => "validation",
+            AppError::Conflict(_) | AppError::ConflictWith(..) => "conflict",
+            AppError::UnsupportedMediaType(_) => "unsupported_media_type",
+            AppError::Upstream(_) => "upstream",
+            AppError::Storage(_) => "storage",
+            AppError::Database(_) => "database",
+            AppError::Internal(_) => "internal",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            AppError::Unauthorized(m)
+            | AppError::Forbidden(m)
+            | AppError::NotFound(m)
+            | AppError::Validation(m)
+            | AppError::Conflict(m)
+            | AppError::ConflictWith(m, _)
+            | AppError::UnsupportedMediaType(m)
+            | AppError::Upstream(m)
+            | AppError::Storage(m) => m.clone(),
+            // don't leak queries or internals to the client; they are logged instead
+            AppError::Database(_) => "Database error".to_string(),
+            AppError::Internal(_) => "Internal server error".to_string(),
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AppError::ConflictWith(_, details) => Some(details.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Database(e) => write!(f, "{}: {}", self.code(), e),
+            AppError::Internal(e) => write!(f, "{}: {:#}", self.code(), e),
+            _ => write!(f, "{}: {}", self.code(), self.message()),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::error!("{}", self);
+        } else {
+            tracing::debug!("{}", self);
+        }
+        let body = ErrorBody {
+            code: self.code(),
+            message: self.message(),
+            request_id: current_request_id(),
+            details: self.details(),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::RowNotFound => AppError::NotFound("Resource not found".to_string()),
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => AppError::Conflict(
+                format!("{} already exists", db_err.table().unwrap_or("resource")),
+            ),
+            sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+                AppError::Validation(format!(
+                    "Referenced resource does not exist ({})",
+                    db_err.constraint().unwrap_or("foreign key")
+                ))
+            }
+            _ => AppError::Database(e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        AppError::Upstream(e.to_string())
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(e: anyhow::Error) -> Self {
+        let e = match e.downcast::<AppError>() {
+            Ok(app_error) => return app_error,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<sqlx::Error>() {
+            Ok(sqlx_error) => return sqlx_error.into(),
+            Err(e) => e,
+        };
+        match e.downcast::<reqwest::Error>() {
+            Ok(reqwest_error) => reqwest_error.into(),
+            Err(e) => AppError::Internal(e),
+        }
+    }
+}
+
+impl From<AuthError> for AppError {
+    fn from(e: AuthError) -> Self {
+        match e {
+            AuthError::Forbidden => AppError::Forbidden(e.message().to_string()),
+            _ => AppError::Unauthorized(e.message().to_string()),
+        }
+    }
+}
+
+impl From<AudioError> for AppError {
+    fn from(e: AudioError) -> Self {
+        match e {
+            AudioError::Unsupported(m) | AudioError::Corrupt(m) => {
+                AppError::UnsupportedMediaType(m)
+            }
+            AudioError::Internal(e) => AppError::Internal(e),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use sqlx::{postgres::PgListener, PgPool};
+use tokio::sync::broadcast;
+
+use crate::db::diary::{DiaryStatusEvent, DIARY_STATUS_CHANNEL};
+
+/// In-process fan-out of diary status transitions, fed by postgres `NOTIFY`
+/// so that transitions made by workers in other instances are seen as well.
+#[derive(Clone)]
+pub struct DiaryEvents {
+    sender: broadcast::Sender<DiaryStatusEvent>,
+}
+
+impl DiaryEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(256);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<DiaryStatusEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn spawn_listener(&self, pool: PgPool) {
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = listen(&pool, &sender).await {
+                    tracing::error!("Diary status listener failed: {}", e);
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        });
+    }
+}
+
+impl Default for DiaryEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn listen(pool: &PgPool, sender: &broadcast::Sender<DiaryStatusEvent>) -> sqlx::Result<()> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(DIARY_STATUS_CHANNEL).await?;
+    loop {
+        let notification = listener.recv().await?;
+        match serde_json::from_str::<DiaryStatusEvent>(notification.payload()) {
+            // no receivers is fine
+            Ok(event) => _ = sender.send(event),
+            Err(e) => tracing::error!("Malformed diary status event: {}", e),
+        }
+    }
+}
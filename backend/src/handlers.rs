@@ -1,5 +1,10 @@
+pub mod ask;
 pub mod calendar;
 pub mod deco;
 pub mod diary;
 pub mod health;
+pub mod report;
 pub mod room;
+pub mod search;
+pub mod share;
+pub mod storage;
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use axum::{debug_handler, extract::State, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::{Date, OffsetDateTime};
+
+use crate::{
+    analysis::{analyzer::TextAnalyzer, ask::DiaryExcerpt},
+    auth::user::AuthUser,
+    db::{diary::get_diaries, diary_embedding::get_user_embeddings},
+    embedding::embedder::{cosine_similarity, Embedder},
+    error::{AppError, AppResult},
+    AppState,
+};
+
+const MAX_QUESTION_CHARS: usize = 500;
+// diaries put in front of the model
+const CONTEXT_DIARIES: usize = 8;
+// per diary, to keep the prompt small
+const EXCERPT_CHARS: usize = 1500;
+
+#[derive(Deserialize, Debug)]
+pub struct AskDiaryBody {
+    question: String,
+    // private diaries are only sent to the model when asked for
+    #[serde(default)]
+    include_private: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Citation {
+    diary_id: i64,
+    local_date: Date,
+    #[serde(with = "time::serde::rfc3339")]
+    created_at: OffsetDateTime,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AskDiaryResponse {
+    answer: String,
+    citations: Vec<Citation>,
+}
+
+// answers a question from the caller's own diaries, citing the ones it used
+#[debug_handler(state = AppState)]
+pub async fn ask_diary(
+    State(pool): State<PgPool>,
+    State(embedder): State<Arc<dyn Embedder>>,
+    State(analyzer): State<Arc<dyn TextAnalyzer>>,
+    AuthUser(user_id): AuthUser,
+    Json(body): Json<AskDiaryBody>,
+) -> AppResult<Json<AskDiaryResponse>> {
+    let question = body.question.trim();
+    if question.is_empty() || question.chars().count() > MAX_QUESTION_CHARS {
+        return Err(AppError::Validation(format!(
+            "question must be between 1 and {} characters",
+            MAX_QUESTION_CHARS
+        )));
+    }
+
+    let query = embedder.embed(question).await.map_err(AppError::upstream)?;
+    let mut scored =
+        get_user_embeddings(&pool, user_id, embedder.model(), None, body.include_private)
+            .await?
+            .into_iter()
+            .map(|diary| (diary.diary_id, cosine_similarity(&query, &diary.embedding)))
+            // unrelated diaries would only invite the model to make things up
+            .filter(|(_, similarity)| *similarity > 0.0)
+            .collect::<Vec<_>>();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(CONTEXT_DIARIES);
+
+    // scoped to the caller again, most relevant first
+    let mut diaries =
+        get_diaries(&pool, user_id, scored.iter().map(|(id, _)| *id).collect()).await?;
+    diaries.sort_by_key(|diary| scored.iter().position(|(id, _)| *id == diary.id));
+    let excerpts = diaries
+        .iter()
+        .filter_map(|diary| {
+            let transcription = diary.transcription.as_deref()?;
+            let text = match diary.summary.as_deref() {
+                Some(summary) => format!("{}\n{}", summary, transcription),
+                None => transcription.to_string(),
+            };
+            Some(DiaryExcerpt {
+                diary_id: diary.id,
+                local_date: diary.local_date,
+                text: text.chars().take(EXCERPT_CHARS).collect(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let answer = analyzer
+        .answer(question, &excerpts)
+        .await
+        .map_err(AppError::upstream)?;
+    let citations = answer
+        .citations
+        .iter()
+        .filter_map(|diary_id| diaries.iter().find(|diary| diary.id == *diary_id))
+        .map(|diary| Citation {
+            diary_id: diary.id,
+            local_date: diary.local_date,
+            created_at: diary.created_at,
+        })
+        .collect();
+    Ok(Json(AskDiaryResponse {
+        answer: answer.answer,
+        citations,
+    }))
+}
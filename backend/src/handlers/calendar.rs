@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use axum::{
     extract::{Query, State},
     response::{IntoResponse, Response},
@@ -6,33 +8,32 @@ use hyper::StatusCode;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use time::OffsetDateTime;
-use uuid::Uuid;
 
-use crate::db::diary::{get_diaries_of_month, Diary};
+use crate::{
+    auth::user::AuthUser,
+    db::diary::get_diaries_of_month,
+    error::AppResult,
+    storage::links::{LinkSigner, SignedDiary},
+};
 
 #[derive(Deserialize, Debug)]
 pub struct GetCalendarParams {
-    user_id: Uuid,
     year: u32,
     month: u32,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Serialize, Debug)]
 struct CalendarData {
     #[serde(with = "time::serde::rfc3339")]
     created_at: OffsetDateTime,
-    diary: Diary,
+    diary: SignedDiary,
 }
 
-pub struct CalendarDataResponse(anyhow::Result<Vec<CalendarData>>);
+pub struct CalendarDataResponse(Vec<CalendarData>);
 
 impl IntoResponse for CalendarDataResponse {
     fn into_response(self) -> Response {
-        let Ok(resp) = &self.0 else {
-            let e = &self.0.unwrap_err();
-            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
-        };
-        let serialized = serde_json::to_string(resp);
+        let serialized = serde_json::to_string(&self.0);
         if let Ok(serialized) = serialized {
             (StatusCode::OK, serialized).into_response()
         } else {
@@ -45,21 +46,23 @@ impl IntoResponse for CalendarDataResponse {
     }
 }
 
-// fetch all calendar entries for the given user & year & month
+// fetch all calendar entries for the caller & year & month
 pub async fn get_calendar(
     State(pool): State<PgPool>,
+    State(link_signer): State<Arc<LinkSigner>>,
+    AuthUser(user_id): AuthUser,
     Query(params): Query<GetCalendarParams>,
-) -> CalendarDataResponse {
-    let diaries = match get_diaries_of_month(&pool, params.year, params.month, params.user_id).await
-    {
-        Ok(diaries) => diaries,
-        Err(e) => return CalendarDataResponse(Err(e)),
-    };
-    CalendarDataResponse(Ok(diaries
-        .into_iter()
-        .map(|d| CalendarData {
-            created_at: d.created_at,
-            diary: d,
-        })
-        .collect()))
+) -> AppResult<CalendarDataResponse> {
+    let diaries = get_diaries_of_month(&pool, params.year, params.month, user_id).await?;
+    Ok(CalendarDataResponse(
+        link_signer
+            .diaries(diaries)
+            .await
+            .into_iter()
+            .map(|d| CalendarData {
+                created_at: d.diary.created_at,
+                diary: d,
+            })
+            .collect(),
+    ))
 }
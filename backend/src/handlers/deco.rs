@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use axum::{
     debug_handler,
     extract::{Multipart, Query, State},
@@ -8,8 +10,16 @@ use serde::Deserialize;
 use sqlx::PgPool;
 
 use crate::{
-    db::deco::Deco,
-    storage::client::SupabaseClient,
+    error::{AppError, AppResult},
+    room::{
+        grid::{Footprint, RoomGrid},
+        theme::ThemeSlot,
+    },
+    storage::{
+        deco::upload_deco_model,
+        links::{LinkSigner, SignedDeco},
+        store::ObjectStore,
+    },
     utils::{parse_multipart::parse_multipart, sqlx::get_pg_tx},
     AppState,
 };
@@ -19,7 +29,7 @@ pub struct GetDecoParams {
     deco_id: i64,
 }
 
-pub struct GetDecoRseponse(Deco);
+pub struct GetDecoRseponse(SignedDeco);
 
 impl IntoResponse for GetDecoRseponse {
     fn into_response(self) -> axum::response::Response {
@@ -36,25 +46,15 @@ impl IntoResponse for GetDecoRseponse {
 #[debug_handler(state = AppState)]
 pub async fn get_deco(
     State(pool): State<PgPool>,
+    State(link_signer): State<Arc<LinkSigner>>,
     Query(params): Query<GetDecoParams>,
-) -> axum::response::Result<GetDecoRseponse> {
+) -> AppResult<GetDecoRseponse> {
     let mut tx = get_pg_tx(pool).await?;
-    let deco = crate::db::deco::get_deco(&mut tx, params.deco_id).await;
-    match deco {
-        Ok(deco) => {
-            if let Err(e) = tx.commit().await {
-                Err(e.to_string().into())
-            } else {
-                Ok(GetDecoRseponse(deco))
-            }
-        }
-        Err(e) => {
-            if let Err(rollback_e) = tx.rollback().await {
-                tracing::error!("Failed to rollback transaction: {}", rollback_e);
-            }
-            Err(e.to_string().into())
-        }
-    }
+    let deco = crate::db::deco::get_deco(&mut tx, params.deco_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Deco"))?;
+    tx.commit().await?;
+    Ok(GetDecoRseponse(link_signer.deco(deco).await))
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -63,9 +63,13 @@ pub struct CreateDecoParams {
     display_name: Option<String>,
     category: Option<String>,
     is_valid: bool,
+    // footprint in room cells, 1x1x1 unless given
+    width: Option<i32>,
+    depth: Option<i32>,
+    height: Option<i32>,
 }
 
-pub struct CreateDecoResponse(Deco);
+pub struct CreateDecoResponse(SignedDeco);
 
 impl IntoResponse for CreateDecoResponse {
     fn into_response(self) -> axum::response::Response {
@@ -82,54 +86,62 @@ impl IntoResponse for CreateDecoResponse {
 #[debug_handler(state = AppState)]
 pub async fn create_deco(
     State(pool): State<PgPool>,
-    State(storage_client): State<SupabaseClient>,
+    State(object_store): State<Arc<dyn ObjectStore>>,
+    State(link_signer): State<Arc<LinkSigner>>,
+    State(room_grid): State<RoomGrid>,
     Query(params): Query<CreateDecoParams>,
     multipart: Multipart,
-) -> axum::response::Result<CreateDecoResponse> {
-    let mut tx = get_pg_tx(pool).await?;
-
-    let result: anyhow::Result<Deco> = async {
-        let (model_bytes, _model_metadata) = match parse_multipart(multipart).await {
-            Ok(model_data) => model_data,
-            Err(e) => return Err(e),
-        };
-        // upload model to storage
-        let url = storage_client
-            .upload_model(model_bytes.to_vec(), &params.name)
-            .await?;
-
-        let deco = crate::db::deco::create_deco(
-            &mut tx,
-            crate::db::deco::CreateDecoParams {
-                name: params.name,
-                display_name: params.display_name,
-                category: params.category,
-                asset_link: url,
-                is_valid: params.is_valid,
-            },
-        )
-        .await?;
-        Ok(deco)
+) -> AppResult<CreateDecoResponse> {
+    let footprint = Footprint {
+        width: params.width.unwrap_or(1) as i64,
+        depth: params.depth.unwrap_or(1) as i64,
+        height: params.height.unwrap_or(1) as i64,
+    };
+    if footprint.width < 1 || footprint.depth < 1 || footprint.height < 1 {
+        return Err(AppError::Validation(
+            "width, depth and height must be positive".to_string(),
+        ));
     }
-    .await;
-    match result {
-        Ok(result) => {
-            if let Err(e) = tx.commit().await {
-                Err(e.to_string().into())
-            } else {
-                Ok(CreateDecoResponse(result))
-            }
-        }
-        Err(e) => {
-            if let Err(rollback_e) = tx.rollback().await {
-                tracing::error!("Failed to rollback transaction: {}", rollback_e);
-            }
-            Err(e.to_string().into())
-        }
+    // theme decos are not placed, a `room_size` one is the room itself
+    let is_theme = ThemeSlot::of_category(params.category.as_deref()).is_some();
+    if !is_theme && !room_grid.fits(footprint) {
+        return Err(AppError::Validation(format!(
+            "A {}x{}x{} deco does not fit in the {}x{}x{} room",
+            footprint.width,
+            footprint.depth,
+            footprint.height,
+            room_grid.width,
+            room_grid.depth,
+            room_grid.height
+        )));
     }
+    let mut tx = get_pg_tx(pool).await?;
+
+    let (model_file, _model_metadata) = parse_multipart(multipart).await?;
+    // upload model to storage
+    upload_deco_model(object_store.as_ref(), &params.name, model_file.path())
+        .await
+        .map_err(AppError::storage)?;
+
+    let deco = crate::db::deco::create_deco(
+        &mut tx,
+        crate::db::deco::CreateDecoParams {
+            name: params.name.clone(),
+            display_name: params.display_name,
+            category: params.category,
+            asset_key: params.name,
+            is_valid: params.is_valid,
+            width: footprint.width as i32,
+            depth: footprint.depth as i32,
+            height: footprint.height as i32,
+        },
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(CreateDecoResponse(link_signer.deco(deco).await))
 }
 
-pub struct GetAvailableDecosResponse(Vec<Deco>);
+pub struct GetAvailableDecosResponse(Vec<SignedDeco>);
 impl IntoResponse for GetAvailableDecosResponse {
     fn into_response(self) -> axum::response::Response {
         let decos = self.0;
@@ -145,22 +157,10 @@ impl IntoResponse for GetAvailableDecosResponse {
 #[debug_handler(state = AppState)]
 pub async fn get_available_decos(
     State(pool): State<PgPool>,
-) -> axum::response::Result<GetAvailableDecosResponse> {
+    State(link_signer): State<Arc<LinkSigner>>,
+) -> AppResult<GetAvailableDecosResponse> {
     let mut tx = get_pg_tx(pool).await?;
-    let decos = crate::db::deco::get_available_decos(&mut tx).await;
-    match decos {
-        Ok(decos) => {
-            if let Err(e) = tx.commit().await {
-                Err(e.to_string().into())
-            } else {
-                Ok(GetAvailableDecosResponse(decos))
-            }
-        }
-        Err(e) => {
-            if let Err(rollback_e) = tx.rollback().await {
-                tracing::error!("Failed to rollback transaction: {}", rollback_e);
-            }
-            Err(e.to_string().into())
-        }
-    }
+    let decos = crate::db::deco::get_available_decos(&mut tx).await?;
+    tx.commit().await?;
+    Ok(GetAvailableDecosResponse(link_signer.decos(decos).await))
 }
@@ -1,30 +1,52 @@
-use std::sync::Arc;
+use std::{convert::Infallible, sync::Arc};
 
 use axum::{
     debug_handler,
     extract::{Multipart, Query, State},
-    response::IntoResponse,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    Json,
 };
+use futures::{stream, Stream};
 use hyper::StatusCode;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use tokio::sync::broadcast::error::RecvError;
 use uuid::Uuid;
 
 use crate::{
-    db::diary::{insert_diary, update_diary, Diary},
-    openai::{client::OpenAIClient, diary::summarize_diary},
-    storage::client::SupabaseClient,
+    audio::pipeline::AudioPipeline,
+    auth::user::AuthUser,
+    db::{
+        diary::{
+            get_diaries, get_trashed_diaries, get_user_diary_for_update, insert_diary,
+            set_diary_audio, set_processing_status, soft_delete_diary, update_diary,
+            DiaryStatusEvent, ProcessingStatus,
+        },
+        diary_segment::{get_diary_segments, replace_diary_segments},
+        job::enqueue_job,
+    },
+    error::{AppError, AppResult},
+    events::DiaryEvents,
+    jobs::{kind::Job, purge::purge_diary},
+    storage::{
+        diary::{delete_diary_audio, upload_diary_audio},
+        links::{LinkSigner, SignedDiary},
+        store::ObjectStore,
+    },
+    transcription::transcript::TranscriptSegment,
     utils::{get_diary_filename, parse_multipart::parse_multipart, sqlx::get_pg_tx},
     AppState,
 };
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct GetDiaryParams {
-    user_id: Uuid,
     diary_id: i64,
 }
 
-pub struct GetDiaryRseponse(Diary);
+pub struct GetDiaryRseponse(SignedDiary);
 
 impl IntoResponse for GetDiaryRseponse {
     fn into_response(self) -> axum::response::Response {
@@ -41,102 +63,371 @@ impl IntoResponse for GetDiaryRseponse {
 #[debug_handler(state = AppState)]
 pub async fn get_diary(
     State(pool): State<PgPool>,
+    State(link_signer): State<Arc<LinkSigner>>,
+    AuthUser(user_id): AuthUser,
     Query(params): Query<GetDiaryParams>,
-) -> axum::response::Result<GetDiaryRseponse> {
-    let diary = crate::db::diary::get_diaries(&pool, params.user_id, vec![params.diary_id]).await;
-    match diary {
-        Ok(diaries) => Ok(GetDiaryRseponse(diaries[0].clone())),
-        Err(e) => Err(e.to_string().into()),
-    }
+) -> AppResult<GetDiaryRseponse> {
+    let diary = crate::db::diary::get_diaries(&pool, user_id, vec![params.diary_id])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::not_found("Diary"))?;
+    Ok(GetDiaryRseponse(link_signer.diary(diary).await))
 }
 
 #[derive(Deserialize, Debug)]
 pub struct CreateDiaryParams {
-    user_id: Uuid,
     is_private: Option<bool>,
 }
 
 #[debug_handler(state = AppState)]
 pub async fn create_diary(
     State(pool): State<PgPool>,
-    State(openai_client): State<Arc<OpenAIClient>>,
-    State(storage_client): State<SupabaseClient>,
+    State(object_store): State<Arc<dyn ObjectStore>>,
+    State(audio_pipeline): State<Arc<AudioPipeline>>,
+    AuthUser(user_id): AuthUser,
     Query(params): Query<CreateDiaryParams>,
     multipart: Multipart,
-) -> axum::response::Result<String> {
-    let mut tx = get_pg_tx(pool.clone()).await?;
-    let result: anyhow::Result<_> = async {
-        let (audio_bytes, _audio_metadata) = match parse_multipart(multipart).await {
-            Ok(audio_data) => audio_data,
-            Err(e) => return Err(e),
-        };
-        // upload diary to database first to retrieve ID
+) -> AppResult<String> {
+    let (audio_file, _audio_metadata) = parse_multipart(multipart).await?;
+    // rejects anything that is not a readable recording before touching the database
+    let audio = audio_pipeline.process(audio_file).await?;
+
+    // stored before the diary exists, so no connection is held during the upload
+    let audio_key = get_diary_filename(user_id, Uuid::new_v4(), audio.format);
+    upload_diary_audio(
+        object_store.as_ref(),
+        &audio_key,
+        audio.file.path(),
+        audio.format.content_type(),
+    )
+    .await
+    .map_err(AppError::storage)?;
+
+    let saved = async {
+        let mut tx = get_pg_tx(pool).await?;
         let diary_id = insert_diary(
             &mut tx,
             crate::db::diary::DiaryParams::new(
-                params.user_id,
+                user_id,
                 None,
                 None,
                 params.is_private.unwrap_or(false),
             ),
         )
         .await?;
-        let audio_title = get_diary_filename(params.user_id, diary_id);
-        let audio_link = storage_client
-            .upload_diary(audio_bytes.to_vec(), &audio_title)
-            .await?;
-        update_diary(&mut tx, diary_id, Some(audio_link), None, None, None, None).await?;
-
-        // create a background subtask to transcribe the audio
-        tokio::spawn(async move {
-            let mut tx = get_pg_tx(pool.clone()).await.unwrap();
-            match openai_client.transcribe(&audio_title, &audio_bytes).await {
-                Ok(audio_transcription) => {
-                    if let Err(e) = update_diary(
-                        &mut tx,
-                        diary_id,
-                        None,
-                        None,
-                        Some(audio_transcription.clone()),
-                        None,
-                        None,
-                    )
-                    .await
-                    {
-                        tracing::error!("Failed to update diary with transcription: {}", e);
-                    } else {
-                        if let Err(e) = tx.commit().await {
-                            tracing::error!("Failed to commit transaction: {}", e);
-                        }
-                        tokio::spawn(async move {
-                            summarize_diary(pool, openai_client, diary_id, audio_transcription)
-                                .await;
-                        });
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("Failed to transcribe audio: {}", e);
-                }
+        set_diary_audio(&mut tx, diary_id, &audio_key, audio.info.as_ref()).await?;
+        // transcription (and then summarization) is picked up by the job workers
+        enqueue_job(&mut tx, Job::TranscribeDiary { diary_id }).await?;
+        tx.commit().await?;
+        AppResult::Ok(diary_id)
+    }
+    .await;
+    let diary_id = match saved {
+        Ok(diary_id) => diary_id,
+        Err(e) => {
+            // no diary points at the recording
+            if let Err(e) = delete_diary_audio(object_store.as_ref(), &audio_key).await {
+                tracing::warn!("Failed to delete unsaved recording {}: {:#}", audio_key, e);
             }
-        });
+            return Err(e);
+        }
+    };
 
-        Ok(diary_id)
+    Ok(diary_id.to_string())
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PatchDiaryParams {
+    diary_id: i64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PatchDiaryBody {
+    transcription: Option<String>,
+    summary: Option<String>,
+    is_private: Option<bool>,
+    // re-run summary & emotion when the transcription changes (default: true)
+    resummarize: Option<bool>,
+}
+
+#[debug_handler(state = AppState)]
+pub async fn patch_diary(
+    State(pool): State<PgPool>,
+    State(link_signer): State<Arc<LinkSigner>>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<PatchDiaryParams>,
+    Json(body): Json<PatchDiaryBody>,
+) -> AppResult<GetDiaryRseponse> {
+    let mut tx = get_pg_tx(pool.clone()).await?;
+    let diary = get_user_diary_for_update(&mut tx, user_id, params.diary_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Diary"))?;
+
+    let transcription_changed = body
+        .transcription
+        .as_ref()
+        .is_some_and(|t| diary.transcription.as_ref() != Some(t));
+    if transcription_changed
+        && matches!(
+            diary.processing_status,
+            ProcessingStatus::Uploaded | ProcessingStatus::Transcribing
+        )
+    {
+        return Err(AppError::Conflict(
+            "Diary is still being transcribed".to_string(),
+        ));
     }
-    .await;
 
-    match result {
-        Ok(id) => {
-            if let Err(e) = tx.commit().await {
-                Err(e.to_string().into())
-            } else {
-                Ok(id.to_string())
-            }
+    update_diary(
+        &mut tx,
+        params.diary_id,
+        None,
+        body.summary,
+        body.transcription,
+        None,
+        body.is_private,
+    )
+    .await?;
+    if transcription_changed {
+        // the timings were for the old text, they cannot be lined up with edits
+        replace_diary_segments(&mut tx, params.diary_id, &[]).await?;
+    }
+    if transcription_changed && body.resummarize.unwrap_or(true) {
+        set_processing_status(
+            &mut tx,
+            params.diary_id,
+            ProcessingStatus::Summarizing,
+            None,
+        )
+        .await?;
+        enqueue_job(
+            &mut tx,
+            Job::SummarizeDiary {
+                diary_id: params.diary_id,
+            },
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    let diary = crate::db::diary::get_diaries(&pool, user_id, vec![params.diary_id])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::not_found("Diary"))?;
+    Ok(GetDiaryRseponse(link_signer.diary(diary).await))
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct DeleteDiaryParams {
+    diary_id: i64,
+}
+
+pub struct DeleteDiaryResponse;
+
+impl IntoResponse for DeleteDiaryResponse {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, "OK").into_response()
+    }
+}
+
+// moves the diary to the trash, see `jobs::purge` for the permanent removal
+#[debug_handler(state = AppState)]
+pub async fn delete_diary(
+    State(pool): State<PgPool>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<DeleteDiaryParams>,
+) -> AppResult<DeleteDiaryResponse> {
+    let mut tx = get_pg_tx(pool).await?;
+    if !soft_delete_diary(&mut tx, user_id, params.diary_id).await? {
+        return Err(AppError::not_found("Diary"));
+    }
+    tx.commit().await?;
+    Ok(DeleteDiaryResponse)
+}
+
+pub struct GetTrashResponse(Vec<SignedDiary>);
+
+impl IntoResponse for GetTrashResponse {
+    fn into_response(self) -> axum::response::Response {
+        let diaries = self.0;
+        let serialized = serde_json::to_string(&diaries);
+
+        match serialized {
+            Ok(serialized) => (StatusCode::OK, serialized).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
         }
-        Err(e) => {
-            if let Err(rollback_e) = tx.rollback().await {
-                tracing::error!("Failed to rollback transaction: {}", rollback_e);
-            }
-            Err(e.to_string().into())
+    }
+}
+
+#[debug_handler(state = AppState)]
+pub async fn get_trash(
+    State(pool): State<PgPool>,
+    State(link_signer): State<Arc<LinkSigner>>,
+    AuthUser(user_id): AuthUser,
+) -> AppResult<GetTrashResponse> {
+    let diaries = get_trashed_diaries(&pool, user_id).await?;
+    Ok(GetTrashResponse(link_signer.diaries(diaries).await))
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TrashedDiaryParams {
+    diary_id: i64,
+}
+
+#[debug_handler(state = AppState)]
+pub async fn restore_diary(
+    State(pool): State<PgPool>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<TrashedDiaryParams>,
+) -> AppResult<DeleteDiaryResponse> {
+    let mut tx = get_pg_tx(pool).await?;
+    if !crate::db::diary::restore_diary(&mut tx, user_id, params.diary_id).await? {
+        return Err(AppError::not_found("Trashed diary"));
+    }
+    tx.commit().await?;
+    Ok(DeleteDiaryResponse)
+}
+
+// empties a single diary from the trash without waiting for the retention window
+#[debug_handler(state = AppState)]
+pub async fn purge_trashed_diary(
+    State(pool): State<PgPool>,
+    State(object_store): State<Arc<dyn ObjectStore>>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<TrashedDiaryParams>,
+) -> AppResult<DeleteDiaryResponse> {
+    if !purge_diary(&pool, object_store.as_ref(), user_id, params.diary_id).await? {
+        return Err(AppError::not_found("Trashed diary"));
+    }
+    Ok(DeleteDiaryResponse)
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct GetDiaryStatusParams {
+    diary_id: i64,
+}
+
+async fn get_diary_status_event(
+    pool: &PgPool,
+    user_id: Uuid,
+    diary_id: i64,
+) -> AppResult<DiaryStatusEvent> {
+    let diary = get_diaries(pool, user_id, vec![diary_id])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::not_found("Diary"))?;
+    Ok(DiaryStatusEvent {
+        diary_id,
+        user_id,
+        processing_status: diary.processing_status,
+        processing_error: diary.processing_error,
+    })
+}
+
+#[debug_handler(state = AppState)]
+pub async fn get_diary_status(
+    State(pool): State<PgPool>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<GetDiaryStatusParams>,
+) -> AppResult<Json<DiaryStatusEvent>> {
+    Ok(Json(
+        get_diary_status_event(&pool, user_id, params.diary_id).await?,
+    ))
+}
+
+struct StatusStreamState {
+    pool: PgPool,
+    user_id: Uuid,
+    diary_id: i64,
+    receiver: tokio::sync::broadcast::Receiver<DiaryStatusEvent>,
+    pending: Option<DiaryStatusEvent>,
+    finished: bool,
+}
+
+// pushes the current status, then every transition until the diary is complete or failed
+#[debug_handler(state = AppState)]
+pub async fn stream_diary_status(
+    State(pool): State<PgPool>,
+    State(diary_events): State<DiaryEvents>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<GetDiaryStatusParams>,
+) -> AppResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    // subscribe before reading the current status so no transition is missed
+    let receiver = diary_events.subscribe();
+    let current = get_diary_status_event(&pool, user_id, params.diary_id).await?;
+
+    let state = StatusStreamState {
+        pool,
+        user_id,
+        diary_id: params.diary_id,
+        receiver,
+        pending: Some(current),
+        finished: false,
+    };
+    let events = stream::unfold(state, |mut state| async move {
+        if state.finished {
+            return None;
         }
+        let event = match state.pending.take() {
+            Some(event) => event,
+            None => loop {
+                match state.receiver.recv().await {
+                    Ok(event) if event.diary_id == state.diary_id => break event,
+                    Ok(_) => continue,
+                    // fell behind; the database has the latest state
+                    Err(RecvError::Lagged(_)) => {
+                        match get_diary_status_event(&state.pool, state.user_id, state.diary_id)
+                            .await
+                        {
+                            Ok(event) => break event,
+                            Err(_) => return None,
+                        }
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            },
+        };
+        state.finished = event.processing_status.is_terminal();
+        let sse_event = Event::default()
+            .event("status")
+            .json_data(&event)
+            .unwrap_or_else(|_| Event::default().event("status"));
+        Some((Ok(sse_event), state))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct GetDiaryTranscriptParams {
+    diary_id: i64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DiaryTranscript {
+    diary_id: i64,
+    // empty until the diary has been transcribed
+    segments: Vec<TranscriptSegment>,
+}
+
+// timed sentences for the player, to follow along and seek by tapping
+#[debug_handler(state = AppState)]
+pub async fn get_diary_transcript(
+    State(pool): State<PgPool>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<GetDiaryTranscriptParams>,
+) -> AppResult<Json<DiaryTranscript>> {
+    if get_diaries(&pool, user_id, vec![params.diary_id])
+        .await?
+        .is_empty()
+    {
+        return Err(AppError::not_found("Diary"));
     }
+    Ok(Json(DiaryTranscript {
+        diary_id: params.diary_id,
+        segments: get_diary_segments(&pool, params.diary_id).await?,
+    }))
 }
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use axum::{
+    debug_handler,
+    extract::{Query, State},
+    Json,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use time::{Date, Month, Weekday};
+use uuid::Uuid;
+
+use crate::{
+    analysis::analyzer::TextAnalyzer,
+    auth::user::AuthUser,
+    db::report::{Report, ReportPeriod},
+    error::{AppError, AppResult},
+    jobs::report::refresh_report,
+    AppState,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct GetMonthlyReportParams {
+    year: i32,
+    month: u8,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GetWeeklyReportParams {
+    year: i32,
+    // ISO 8601 week number, weeks start on monday
+    week: u8,
+}
+
+async fn report_of(
+    pool: &PgPool,
+    analyzer: &dyn TextAnalyzer,
+    user_id: Uuid,
+    period: ReportPeriod,
+    start: Date,
+) -> AppResult<Json<Report>> {
+    refresh_report(pool, analyzer, user_id, period, start)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::not_found("Report"))
+}
+
+// written on first request and rewritten whenever the month's diaries changed
+#[debug_handler(state = AppState)]
+pub async fn get_monthly_report(
+    State(pool): State<PgPool>,
+    State(analyzer): State<Arc<dyn TextAnalyzer>>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<GetMonthlyReportParams>,
+) -> AppResult<Json<Report>> {
+    let start = Month::try_from(params.month)
+        .and_then(|month| Date::from_calendar_date(params.year, month, 1))
+        .map_err(|_| AppError::Validation("Invalid year or month".to_string()))?;
+    report_of(
+        &pool,
+        analyzer.as_ref(),
+        user_id,
+        ReportPeriod::Month,
+        start,
+    )
+    .await
+}
+
+#[debug_handler(state = AppState)]
+pub async fn get_weekly_report(
+    State(pool): State<PgPool>,
+    State(analyzer): State<Arc<dyn TextAnalyzer>>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<GetWeeklyReportParams>,
+) -> AppResult<Json<Report>> {
+    let start = Date::from_iso_week_date(params.year, params.week, Weekday::Monday)
+        .map_err(|_| AppError::Validation("Invalid year or week".to_string()))?;
+    report_of(&pool, analyzer.as_ref(), user_id, ReportPeriod::Week, start).await
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use axum::{
     debug_handler,
     extract::{Query, State},
@@ -5,28 +7,84 @@ use axum::{
     Json,
 };
 use hyper::StatusCode;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sqlx::PgPool;
-use uuid::Uuid;
+use time::OffsetDateTime;
 
 use crate::{
-    db::user_deco::UserDeco,
+    auth::user::AuthUser,
+    db::{
+        deco::get_deco,
+        room::{
+            get_layout_snapshot, get_layout_snapshots, get_layout_version, get_room_theme,
+            lock_room, LayoutSnapshotSummary, RoomTheme,
+        },
+        user_deco::{get_placed_deco, get_room_decos, get_user_deco_of_month},
+    },
+    error::{AppError, AppResult},
+    room::{
+        grid::RoomGrid,
+        history::RoomHistory,
+        layout::{apply_layout, check_layout, LayoutPlacement},
+        placement::check_placement,
+        theme::{room_grid, ThemeDecos, ThemeSlot},
+    },
+    storage::links::{LinkSigner, SignedDeco, SignedUserDeco},
     utils::{coordinates::Coordinates, sqlx::get_pg_tx},
     AppState,
 };
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct GetRoomParams {
-    user_id: Uuid,
+    pub year: i32,
+    pub month: u32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RoomThemeView {
+    wallpaper: Option<SignedDeco>,
+    floor: Option<SignedDeco>,
+    lighting: Option<SignedDeco>,
+    size: Option<SignedDeco>,
+    // cells decos are placed on, from `size` or the default
+    grid: RoomGrid,
+}
+
+impl RoomThemeView {
+    pub async fn sign(link_signer: &LinkSigner, decos: ThemeDecos, default: RoomGrid) -> Self {
+        let grid = decos.grid(default);
+        let sign = |deco: Option<_>| async move {
+            match deco {
+                Some(deco) => Some(link_signer.deco(deco).await),
+                None => None,
+            }
+        };
+        Self {
+            wallpaper: sign(decos.wallpaper).await,
+            floor: sign(decos.floor).await,
+            lighting: sign(decos.lighting).await,
+            size: sign(decos.size).await,
+            grid,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct Room {
     year: i32,
     month: u32,
+    // sent back with `PUT /room/layout`
+    version: i64,
+    theme: RoomThemeView,
+    decos: Vec<SignedUserDeco>,
 }
 
-pub struct GetRoomResponse(Vec<UserDeco>);
+pub struct GetRoomResponse(Room);
 impl IntoResponse for GetRoomResponse {
     fn into_response(self) -> axum::response::Response {
-        let user_decos = self.0;
-        let serialized = serde_json::to_string(&user_decos);
+        let room = self.0;
+        let serialized = serde_json::to_string(&room);
 
         match serialized {
             Ok(serialized) => (StatusCode::OK, serialized).into_response(),
@@ -38,36 +96,28 @@ impl IntoResponse for GetRoomResponse {
 #[debug_handler(state = AppState)]
 pub async fn get_room(
     State(pool): State<PgPool>,
+    State(link_signer): State<Arc<LinkSigner>>,
+    State(default_grid): State<RoomGrid>,
+    AuthUser(user_id): AuthUser,
     Query(params): Query<GetRoomParams>,
-) -> axum::response::Result<GetRoomResponse> {
+) -> AppResult<GetRoomResponse> {
     let mut tx = get_pg_tx(pool).await?;
-    let user_decos = crate::db::user_deco::get_user_deco_of_month(
-        &mut tx,
-        params.user_id,
-        params.year,
-        params.month,
-    )
-    .await;
-    match user_decos {
-        Ok(user_decos) => {
-            if let Err(e) = tx.commit().await {
-                Err(e.to_string().into())
-            } else {
-                Ok(GetRoomResponse(user_decos))
-            }
-        }
-        Err(e) => {
-            if let Err(rollback_e) = tx.rollback().await {
-                tracing::error!("Failed to rollback transaction: {}", rollback_e);
-            }
-            Err(e.to_string().into())
-        }
-    }
+    let user_decos = get_user_deco_of_month(&mut tx, user_id, params.year, params.month).await?;
+    let version = get_layout_version(&mut tx, user_id, params.year, params.month).await?;
+    let theme = get_room_theme(&mut tx, user_id, params.year, params.month).await?;
+    let theme_decos = ThemeDecos::load(&mut tx, &theme).await?;
+    tx.commit().await?;
+    Ok(GetRoomResponse(Room {
+        year: params.year,
+        month: params.month,
+        version,
+        theme: RoomThemeView::sign(&link_signer, theme_decos, default_grid).await,
+        decos: link_signer.user_decos(user_decos).await,
+    }))
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct CreateDecoParams {
-    user_id: Uuid,
     diary_id: i64,
     deco_id: i64,
 }
@@ -82,36 +132,31 @@ impl IntoResponse for CreateDecoResponse {
 #[debug_handler(state = AppState)]
 pub async fn create_user_deco(
     State(pool): State<PgPool>,
+    AuthUser(user_id): AuthUser,
     Query(params): Query<CreateDecoParams>,
-) -> axum::response::Result<CreateDecoResponse> {
+) -> AppResult<CreateDecoResponse> {
     let mut tx = get_pg_tx(pool).await?;
-    let res = crate::db::user_deco::create_user_deco(
-        &mut tx,
-        params.user_id,
-        params.diary_id,
-        params.deco_id,
-    )
-    .await;
-
-    match res {
-        Ok(_) => {
-            if let Err(e) = tx.commit().await {
-                return Err(e.to_string().into());
-            }
-            Ok(CreateDecoResponse)
-        }
-        Err(e) => {
-            if let Err(rollback_e) = tx.rollback().await {
-                tracing::error!("Failed to rollback transaction: {}", rollback_e);
-            }
-            Err(e.to_string().into())
-        }
+    let deco = get_deco(&mut tx, params.deco_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Deco"))?;
+    if let Some(slot) = ThemeSlot::of_category(deco.category.as_deref()) {
+        return Err(AppError::Validation(format!(
+            "{} is a {} deco, set it with PUT /room/theme",
+            deco.name,
+            slot.category()
+        )));
     }
+    if !crate::db::user_deco::create_user_deco(&mut tx, user_id, params.diary_id, params.deco_id)
+        .await?
+    {
+        return Err(AppError::not_found("Diary"));
+    }
+    tx.commit().await?;
+    Ok(CreateDecoResponse)
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct UpdateRoomParams {
-    user_id: Uuid,
     diary_id: i64,
     deco_id: i64,
 }
@@ -127,31 +172,273 @@ impl IntoResponse for UpdateRoomResponse {
 #[debug_handler(state = AppState)]
 pub async fn update_user_deco(
     State(pool): State<PgPool>,
+    State(default_grid): State<RoomGrid>,
+    State(room_history): State<RoomHistory>,
+    AuthUser(user_id): AuthUser,
     Query(params): Query<UpdateRoomParams>,
     Json(coordinates): Json<Option<Coordinates>>,
-) -> axum::response::Result<UpdateRoomResponse> {
+) -> AppResult<UpdateRoomResponse> {
     let mut tx = get_pg_tx(pool).await?;
-    let res = crate::db::user_deco::update_user_deco(
+    let target = get_placed_deco(&mut tx, user_id, params.diary_id, params.deco_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("User deco"))?;
+    let (year, month) = target.room();
+    room_history.begin(&mut tx, user_id, year, month).await?;
+    // taking a deco out of the room always works
+    if let Some(coordinates) = &coordinates {
+        let grid = room_grid(&mut tx, default_grid, user_id, year, month).await?;
+        check_placement(&mut tx, &grid, user_id, &target, coordinates).await?;
+    }
+    crate::db::user_deco::update_user_deco(
         &mut tx,
-        params.user_id,
+        user_id,
         params.diary_id,
         params.deco_id,
         coordinates,
     )
-    .await;
+    .await?;
+    room_history.commit(&mut tx, user_id, year, month).await?;
+    tx.commit().await?;
+    Ok(UpdateRoomResponse)
+}
 
-    match res {
-        Ok(_) => {
-            if let Err(e) = tx.commit().await {
-                return Err(e.to_string().into());
-            }
-            Ok(UpdateRoomResponse)
+fn check_month(month: u32) -> AppResult<()> {
+    if !(1..=12).contains(&month) {
+        return Err(AppError::Validation(
+            "month must be between 1 and 12".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// saves only apply to the version of the room they were made from
+fn check_version(current: i64, expected: i64) -> AppResult<()> {
+    if current != expected {
+        return Err(AppError::ConflictWith(
+            "The room was changed since it was loaded".to_string(),
+            json!({ "version": current }),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SaveRoomLayoutBody {
+    year: i32,
+    month: u32,
+    // `version` of the room the layout was made from
+    version: i64,
+    // decos left out are taken out of the room
+    placements: Vec<LayoutPlacement>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SaveRoomLayoutResponse {
+    version: i64,
+}
+
+// replaces every placement of a month's room at once
+#[debug_handler(state = AppState)]
+pub async fn save_room_layout(
+    State(pool): State<PgPool>,
+    State(default_grid): State<RoomGrid>,
+    State(room_history): State<RoomHistory>,
+    AuthUser(user_id): AuthUser,
+    Json(body): Json<SaveRoomLayoutBody>,
+) -> AppResult<Json<SaveRoomLayoutResponse>> {
+    check_month(body.month)?;
+
+    let mut tx = get_pg_tx(pool).await?;
+    let version = room_history
+        .begin(&mut tx, user_id, body.year, body.month)
+        .await?;
+    check_version(version, body.version)?;
+    let decos = get_room_decos(&mut tx, user_id, body.year, body.month).await?;
+    let grid = room_grid(&mut tx, default_grid, user_id, body.year, body.month).await?;
+    apply_layout(&mut tx, &grid, user_id, &decos, &body.placements).await?;
+    let version = room_history
+        .commit(&mut tx, user_id, body.year, body.month)
+        .await?;
+    tx.commit().await?;
+    Ok(Json(SaveRoomLayoutResponse { version }))
+}
+
+// earlier layouts of a month's room, newest first
+#[debug_handler(state = AppState)]
+pub async fn get_room_history(
+    State(pool): State<PgPool>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<GetRoomParams>,
+) -> AppResult<Json<Vec<LayoutSnapshotSummary>>> {
+    let snapshots = get_layout_snapshots(&pool, user_id, params.year, params.month).await?;
+    Ok(Json(snapshots))
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct RoomSnapshotParams {
+    year: i32,
+    month: u32,
+    version: i64,
+}
+
+#[derive(Serialize, Debug)]
+struct RoomSnapshot {
+    year: i32,
+    month: u32,
+    version: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    created_at: OffsetDateTime,
+    decos: Vec<SignedUserDeco>,
+}
+
+pub struct GetRoomSnapshotResponse(RoomSnapshot);
+impl IntoResponse for GetRoomSnapshotResponse {
+    fn into_response(self) -> axum::response::Response {
+        let serialized = serde_json::to_string(&self.0);
+
+        match serialized {
+            Ok(serialized) => (StatusCode::OK, serialized).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
         }
-        Err(e) => {
-            if let Err(rollback_e) = tx.rollback().await {
-                tracing::error!("Failed to rollback transaction: {}", rollback_e);
-            }
-            Err(e.to_string().into())
+    }
+}
+
+// the room's decos as they were at an earlier version; decos of diaries
+// deleted since are left out
+#[debug_handler(state = AppState)]
+pub async fn get_room_snapshot(
+    State(pool): State<PgPool>,
+    State(link_signer): State<Arc<LinkSigner>>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<RoomSnapshotParams>,
+) -> AppResult<GetRoomSnapshotResponse> {
+    let mut tx = get_pg_tx(pool).await?;
+    let snapshot = get_layout_snapshot(&mut tx, user_id, params.year, params.month, params.version)
+        .await?
+        .ok_or_else(|| AppError::not_found("Layout"))?;
+    let user_decos = get_user_deco_of_month(&mut tx, user_id, params.year, params.month)
+        .await?
+        .into_iter()
+        .map(|user_deco| {
+            let coordinates = snapshot
+                .placements
+                .iter()
+                .find(|placement| (placement.diary_id, placement.deco_id) == user_deco.key())
+                .map(|placement| placement.coordinates.clone());
+            user_deco.placed_at(coordinates)
+        })
+        .collect();
+    tx.commit().await?;
+    Ok(GetRoomSnapshotResponse(RoomSnapshot {
+        year: params.year,
+        month: params.month,
+        version: snapshot.version,
+        created_at: snapshot.created_at,
+        decos: link_signer.user_decos(user_decos).await,
+    }))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RestoreRoomLayoutBody {
+    year: i32,
+    month: u32,
+    // current `version` of the room, as for saves
+    version: i64,
+    // the earlier version to bring back
+    restore_version: i64,
+}
+
+// brings back an earlier layout as a new version, so the restore can be undone too
+#[debug_handler(state = AppState)]
+pub async fn restore_room_layout(
+    State(pool): State<PgPool>,
+    State(default_grid): State<RoomGrid>,
+    State(room_history): State<RoomHistory>,
+    AuthUser(user_id): AuthUser,
+    Json(body): Json<RestoreRoomLayoutBody>,
+) -> AppResult<Json<SaveRoomLayoutResponse>> {
+    check_month(body.month)?;
+
+    let mut tx = get_pg_tx(pool).await?;
+    let version = room_history
+        .begin(&mut tx, user_id, body.year, body.month)
+        .await?;
+    check_version(version, body.version)?;
+    let snapshot = get_layout_snapshot(
+        &mut tx,
+        user_id,
+        body.year,
+        body.month,
+        body.restore_version,
+    )
+    .await?
+    .ok_or_else(|| AppError::not_found("Layout"))?;
+    let decos = get_room_decos(&mut tx, user_id, body.year, body.month).await?;
+    // decos of diaries deleted since stay gone
+    let placements = snapshot
+        .placements
+        .0
+        .into_iter()
+        .filter(|placement| {
+            decos.iter().any(|deco| {
+                (deco.diary_id, deco.deco_id) == (placement.diary_id, placement.deco_id)
+            })
+        })
+        .collect::<Vec<_>>();
+    let grid = room_grid(&mut tx, default_grid, user_id, body.year, body.month).await?;
+    apply_layout(&mut tx, &grid, user_id, &decos, &placements).await?;
+    let version = room_history
+        .commit(&mut tx, user_id, body.year, body.month)
+        .await?;
+    tx.commit().await?;
+    Ok(Json(SaveRoomLayoutResponse { version }))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpdateRoomThemeBody {
+    year: i32,
+    month: u32,
+    // the whole theme, null parts go back to the default
+    #[serde(flatten)]
+    theme: RoomTheme,
+}
+
+pub struct UpdateRoomThemeResponse(RoomThemeView);
+impl IntoResponse for UpdateRoomThemeResponse {
+    fn into_response(self) -> axum::response::Response {
+        let serialized = serde_json::to_string(&self.0);
+
+        match serialized {
+            Ok(serialized) => (StatusCode::OK, serialized).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
         }
     }
 }
+
+// sets the wallpaper, floor, lighting and size of a month's room
+#[debug_handler(state = AppState)]
+pub async fn update_room_theme(
+    State(pool): State<PgPool>,
+    State(link_signer): State<Arc<LinkSigner>>,
+    State(default_grid): State<RoomGrid>,
+    AuthUser(user_id): AuthUser,
+    Json(body): Json<UpdateRoomThemeBody>,
+) -> AppResult<UpdateRoomThemeResponse> {
+    check_month(body.month)?;
+
+    let mut tx = get_pg_tx(pool).await?;
+    // a resize must not race a layout change
+    lock_room(&mut tx, user_id, body.year, body.month).await?;
+    let theme_decos = ThemeDecos::load_checked(&mut tx, &body.theme).await?;
+    let grid = theme_decos.grid(default_grid);
+    if grid != room_grid(&mut tx, default_grid, user_id, body.year, body.month).await? {
+        // the decos already in the room have to fit the new size
+        let decos = get_room_decos(&mut tx, user_id, body.year, body.month).await?;
+        check_layout(&grid, &decos, &LayoutPlacement::of_room(&decos))?;
+    }
+    crate::db::room::update_room_theme(&mut tx, user_id, body.year, body.month, body.theme).await?;
+    tx.commit().await?;
+    Ok(UpdateRoomThemeResponse(
+        RoomThemeView::sign(&link_signer, theme_decos, default_grid).await,
+    ))
+}
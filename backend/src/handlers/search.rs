@@ -0,0 +1,259 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    debug_handler,
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::{Date, OffsetDateTime};
+
+use crate::{
+    analysis::emotion::Emotion,
+    auth::user::AuthUser,
+    db::{
+        diary::{get_diaries, search_diaries, DiarySearch},
+        diary_embedding::{get_diary_embedding, get_user_embeddings},
+    },
+    embedding::embedder::cosine_similarity,
+    error::{AppError, AppResult},
+    storage::links::{LinkSigner, SignedDiary},
+    utils::snippet::{snippet, Snippet},
+    AppState,
+};
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 50;
+const MAX_TERMS: usize = 8;
+
+#[derive(Deserialize, Debug)]
+pub struct SearchDiaryParams {
+    // whitespace separated terms, all of which must match
+    q: Option<String>,
+    // inclusive bounds on the diary's local date, `YYYY-MM-DD`
+    from: Option<Date>,
+    to: Option<Date>,
+    emotion: Option<Emotion>,
+    is_private: Option<bool>,
+    // `next_cursor` of the previous page
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+struct SearchHit {
+    #[serde(flatten)]
+    diary: SignedDiary,
+    summary_snippet: Option<Snippet>,
+    transcription_snippet: Option<Snippet>,
+}
+
+#[derive(Serialize, Debug)]
+struct SearchPage {
+    results: Vec<SearchHit>,
+    // absent on the last page
+    next_cursor: Option<String>,
+}
+
+pub struct SearchDiaryResponse(SearchPage);
+
+impl IntoResponse for SearchDiaryResponse {
+    fn into_response(self) -> Response {
+        let serialized = serde_json::to_string(&self.0);
+        match serialized {
+            Ok(serialized) => (StatusCode::OK, serialized).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+}
+
+fn encode_cursor(created_at: OffsetDateTime, id: i64) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}:{}", created_at.unix_timestamp_nanos(), id))
+}
+
+fn decode_cursor(cursor: &str) -> Option<(OffsetDateTime, i64)> {
+    let decoded = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+    let (nanos, id) = decoded.split_once(':')?;
+    Some((
+        OffsetDateTime::from_unix_timestamp_nanos(nanos.parse().ok()?).ok()?,
+        id.parse().ok()?,
+    ))
+}
+
+// searches the caller's diaries, newest first
+#[debug_handler(state = AppState)]
+pub async fn search_diary(
+    State(pool): State<PgPool>,
+    State(link_signer): State<Arc<LinkSigner>>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<SearchDiaryParams>,
+) -> AppResult<SearchDiaryResponse> {
+    let terms = params
+        .q
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if terms.len() > MAX_TERMS {
+        return Err(AppError::Validation(format!(
+            "At most {} search terms are allowed",
+            MAX_TERMS
+        )));
+    }
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(AppError::Validation(format!(
+            "limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+    if let (Some(from), Some(to)) = (params.from, params.to) {
+        if from > to {
+            return Err(AppError::Validation("from is after to".to_string()));
+        }
+    }
+    let after = match params.cursor.as_deref() {
+        Some(cursor) => Some(
+            decode_cursor(cursor)
+                .ok_or_else(|| AppError::Validation("Invalid cursor".to_string()))?,
+        ),
+        None => None,
+    };
+
+    // one extra row tells whether there is a next page
+    let mut diaries = search_diaries(
+        &pool,
+        user_id,
+        &DiarySearch {
+            terms: terms.clone(),
+            from: params.from,
+            to: params.to,
+            emotion: params.emotion,
+            is_private: params.is_private,
+            after,
+            limit: limit + 1,
+        },
+    )
+    .await?;
+    let next_cursor = if diaries.len() as i64 > limit {
+        diaries.truncate(limit as usize);
+        diaries
+            .last()
+            .map(|diary| encode_cursor(diary.created_at, diary.id))
+    } else {
+        None
+    };
+
+    let results = link_signer
+        .diaries(diaries)
+        .await
+        .into_iter()
+        .map(|diary| SearchHit {
+            summary_snippet: diary
+                .diary
+                .summary
+                .as_deref()
+                .and_then(|summary| snippet(summary, &terms)),
+            transcription_snippet: diary
+                .diary
+                .transcription
+                .as_deref()
+                .and_then(|transcription| snippet(transcription, &terms)),
+            diary,
+        })
+        .collect();
+    Ok(SearchDiaryResponse(SearchPage {
+        results,
+        next_cursor,
+    }))
+}
+
+const DEFAULT_SIMILAR: usize = 5;
+const MAX_SIMILAR: usize = 20;
+
+#[derive(Deserialize, Debug)]
+pub struct SimilarDiaryParams {
+    diary_id: i64,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+struct SimilarDiary {
+    #[serde(flatten)]
+    diary: SignedDiary,
+    // cosine similarity of the embeddings, higher is closer
+    similarity: f32,
+}
+
+pub struct SimilarDiaryResponse(Vec<SimilarDiary>);
+
+impl IntoResponse for SimilarDiaryResponse {
+    fn into_response(self) -> Response {
+        let serialized = serde_json::to_string(&self.0);
+        match serialized {
+            Ok(serialized) => (StatusCode::OK, serialized).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+}
+
+// the caller's earlier diaries closest in meaning to the given one, closest first
+#[debug_handler(state = AppState)]
+pub async fn get_similar_diaries(
+    State(pool): State<PgPool>,
+    State(link_signer): State<Arc<LinkSigner>>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<SimilarDiaryParams>,
+) -> AppResult<SimilarDiaryResponse> {
+    let limit = params.limit.unwrap_or(DEFAULT_SIMILAR);
+    if !(1..=MAX_SIMILAR).contains(&limit) {
+        return Err(AppError::Validation(format!(
+            "limit must be between 1 and {}",
+            MAX_SIMILAR
+        )));
+    }
+    let diary = get_diaries(&pool, user_id, vec![params.diary_id])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::not_found("Diary"))?;
+    // embedded once the summary is done
+    let target = get_diary_embedding(&pool, diary.id)
+        .await?
+        .ok_or_else(|| AppError::Conflict("Diary has not been indexed yet".to_string()))?;
+
+    // a user has at most a few thousand diaries, a scan is cheap enough
+    let mut scored =
+        get_user_embeddings(&pool, user_id, &target.model, Some(diary.created_at), true)
+            .await?
+            .into_iter()
+            .map(|other| {
+                (
+                    other.diary_id,
+                    cosine_similarity(&target.embedding, &other.embedding),
+                )
+            })
+            .collect::<Vec<_>>();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(limit);
+
+    let similarity = scored.iter().copied().collect::<HashMap<_, _>>();
+    let mut diaries =
+        get_diaries(&pool, user_id, scored.iter().map(|(id, _)| *id).collect()).await?;
+    diaries.sort_by(|a, b| similarity[&b.id].total_cmp(&similarity[&a.id]));
+    Ok(SimilarDiaryResponse(
+        link_signer
+            .diaries(diaries)
+            .await
+            .into_iter()
+            .map(|diary| SimilarDiary {
+                similarity: similarity[&diary.diary.id],
+                diary,
+            })
+            .collect(),
+    ))
+}
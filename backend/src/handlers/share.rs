@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use axum::{
+    debug_handler,
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use futures::future::join_all;
+use hyper::StatusCode;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use time::{Date, OffsetDateTime};
+
+use crate::{
+    auth::user::AuthUser,
+    db::{
+        room::get_room_theme,
+        room_share::{
+            create_room_share, get_active_room_share, get_room_shares, revoke_room_share, RoomShare,
+        },
+        user_deco::{get_user_deco_of_month, UserDeco},
+    },
+    error::{AppError, AppResult},
+    room::{grid::RoomGrid, theme::ThemeDecos},
+    storage::{links::LinkSigner, store::Bucket},
+    utils::{coordinates::Coordinates, sqlx::get_pg_tx},
+    AppState,
+};
+
+use super::room::{GetRoomParams, RoomThemeView};
+
+// 256 random bits, only ever shown once
+fn new_token() -> String {
+    let mut bytes = [0; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateRoomShareBody {
+    year: i32,
+    month: u32,
+    // the link works until revoked when absent
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    expires_at: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CreatedRoomShare {
+    #[serde(flatten)]
+    share: RoomShare,
+    // for `GET /shared/room/:token`, not stored
+    token: String,
+}
+
+// mints a public read-only link to a month's room
+#[debug_handler(state = AppState)]
+pub async fn create_share(
+    State(pool): State<PgPool>,
+    AuthUser(user_id): AuthUser,
+    Json(body): Json<CreateRoomShareBody>,
+) -> AppResult<Json<CreatedRoomShare>> {
+    if !(1..=12).contains(&body.month) {
+        return Err(AppError::Validation(
+            "month must be between 1 and 12".to_string(),
+        ));
+    }
+    if body
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc())
+    {
+        return Err(AppError::Validation(
+            "expires_at must be in the future".to_string(),
+        ));
+    }
+
+    let token = new_token();
+    let mut tx = get_pg_tx(pool).await?;
+    let share = create_room_share(
+        &mut tx,
+        user_id,
+        body.year,
+        body.month,
+        &hash_token(&token),
+        body.expires_at,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(Json(CreatedRoomShare { share, token }))
+}
+
+// the links to a month's room that still work
+#[debug_handler(state = AppState)]
+pub async fn get_shares(
+    State(pool): State<PgPool>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<GetRoomParams>,
+) -> AppResult<Json<Vec<RoomShare>>> {
+    let shares = get_room_shares(&pool, user_id, params.year, params.month).await?;
+    Ok(Json(shares))
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct RevokeShareParams {
+    share_id: i64,
+}
+
+pub struct RevokeShareResponse;
+
+impl IntoResponse for RevokeShareResponse {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::OK, "OK").into_response()
+    }
+}
+
+#[debug_handler(state = AppState)]
+pub async fn revoke_share(
+    State(pool): State<PgPool>,
+    AuthUser(user_id): AuthUser,
+    Query(params): Query<RevokeShareParams>,
+) -> AppResult<RevokeShareResponse> {
+    let mut tx = get_pg_tx(pool).await?;
+    if !revoke_room_share(&mut tx, user_id, params.share_id).await? {
+        return Err(AppError::not_found("Room share"));
+    }
+    tx.commit().await?;
+    Ok(RevokeShareResponse)
+}
+
+/// A placed deco of a public diary as strangers see it: nothing of the diary
+/// but its date.
+#[derive(Serialize, Debug)]
+struct SharedDeco {
+    deco_id: i64,
+    name: String,
+    category: Option<String>,
+    display_name: Option<String>,
+    width: i32,
+    depth: i32,
+    height: i32,
+    asset_link: Option<String>,
+    local_date: Date,
+    coordinates: Option<Coordinates>,
+}
+
+impl SharedDeco {
+    async fn sign(link_signer: &LinkSigner, user_deco: UserDeco) -> Self {
+        let asset_link = match user_deco.asset_key.as_deref() {
+            Some(key) => link_signer.sign(Bucket::Model, key).await,
+            None => None,
+        };
+        Self {
+            deco_id: user_deco.deco_id,
+            name: user_deco.name,
+            category: user_deco.category,
+            display_name: user_deco.display_name,
+            width: user_deco.width,
+            depth: user_deco.depth,
+            height: user_deco.height,
+            asset_link,
+            local_date: user_deco.local_date,
+            coordinates: user_deco.coordinates.map(|coordinates| coordinates.0),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct SharedRoom {
+    year: i32,
+    month: i32,
+    theme: RoomThemeView,
+    decos: Vec<SharedDeco>,
+}
+
+pub struct GetSharedRoomResponse(SharedRoom);
+impl IntoResponse for GetSharedRoomResponse {
+    fn into_response(self) -> axum::response::Response {
+        let serialized = serde_json::to_string(&self.0);
+
+        match serialized {
+            Ok(serialized) => (StatusCode::OK, serialized).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+}
+
+// public: the token is the only credential
+#[debug_handler(state = AppState)]
+pub async fn get_shared_room(
+    State(pool): State<PgPool>,
+    State(link_signer): State<Arc<LinkSigner>>,
+    State(default_grid): State<RoomGrid>,
+    Path(token): Path<String>,
+) -> AppResult<GetSharedRoomResponse> {
+    let mut tx = get_pg_tx(pool).await?;
+    // revoked, expired and unknown links look the same
+    let share = get_active_room_share(&mut tx, &hash_token(&token))
+        .await?
+        .ok_or_else(|| AppError::not_found("Shared room"))?;
+    let month = share.month as u32;
+    let user_decos = get_user_deco_of_month(&mut tx, share.user_id, share.year, month)
+        .await?
+        .into_iter()
+        // private diaries leave no trace, not even their decos
+        .filter(|user_deco| user_deco.is_placed && !user_deco.is_private)
+        .collect::<Vec<_>>();
+    let theme = get_room_theme(&mut tx, share.user_id, share.year, month).await?;
+    let theme_decos = ThemeDecos::load(&mut tx, &theme).await?;
+    tx.commit().await?;
+
+    let decos = join_all(
+        user_decos
+            .into_iter()
+            .map(|user_deco| SharedDeco::sign(&link_signer, user_deco)),
+    )
+    .await;
+    Ok(GetSharedRoomResponse(SharedRoom {
+        year: share.year,
+        month: share.month,
+        theme: RoomThemeView::sign(&link_signer, theme_decos, default_grid).await,
+        decos,
+    }))
+}
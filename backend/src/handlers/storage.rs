@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    debug_handler,
+    extract::{Path, Query, State},
+    http::header,
+    response::IntoResponse,
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    error::{AppError, AppResult},
+    storage::store::{Bucket, ObjectStore},
+    AppState,
+};
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SignedObjectParams {
+    expires: u64,
+    signature: String,
+}
+
+pub struct GetObjectResponse(tokio::fs::File);
+
+impl IntoResponse for GetObjectResponse {
+    fn into_response(self) -> axum::response::Response {
+        (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/octet-stream")],
+            Body::from_stream(ReaderStream::new(self.0)),
+        )
+            .into_response()
+    }
+}
+
+// serves the signed URLs minted by the local storage backend
+#[debug_handler(state = AppState)]
+pub async fn get_signed_object(
+    State(object_store): State<Arc<dyn ObjectStore>>,
+    Path((bucket, key)): Path<(String, String)>,
+    Query(params): Query<SignedObjectParams>,
+) -> AppResult<GetObjectResponse> {
+    let (Some(local_store), Some(bucket)) = (object_store.as_local(), Bucket::from_name(&bucket))
+    else {
+        return Err(AppError::not_found("Object"));
+    };
+    if !local_store.verify(bucket, &key, params.expires, &params.signature) {
+        return Err(AppError::Forbidden(
+            "Invalid or expired signature".to_string(),
+        ));
+    }
+    let path = local_store.path(bucket, &key).map_err(AppError::Internal)?;
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|_| AppError::not_found("Object"))?;
+    Ok(GetObjectResponse(file))
+}
@@ -0,0 +1,4 @@
+pub mod kind;
+pub mod purge;
+pub mod report;
+pub mod worker;
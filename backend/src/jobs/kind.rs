@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use axum::extract::FromRef;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::Date;
+use uuid::Uuid;
+
+use crate::{
+    analysis::analyzer::TextAnalyzer,
+    db::report::ReportPeriod,
+    embedding::embedder::Embedder,
+    jobs::report::refresh_report,
+    openai::diary::{embed_diary, fail_diary, summarize_diary, transcribe_diary},
+    storage::store::ObjectStore,
+    transcription::chunked::ChunkedTranscriber,
+    AppState,
+};
+
+/// A unit of background work, stored as the `payload` of a `job` row.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Job {
+    TranscribeDiary {
+        diary_id: i64,
+    },
+    SummarizeDiary {
+        diary_id: i64,
+    },
+    EmbedDiary {
+        diary_id: i64,
+    },
+    GenerateReport {
+        user_id: Uuid,
+        period: ReportPeriod,
+        period_start: Date,
+    },
+}
+
+impl Job {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Job::TranscribeDiary { .. } => "transcribe_diary",
+            Job::SummarizeDiary { .. } => "summarize_diary",
+            Job::EmbedDiary { .. } => "embed_diary",
+            Job::GenerateReport { .. } => "generate_report",
+        }
+    }
+
+    pub fn max_attempts(&self) -> i32 {
+        5
+    }
+
+    pub async fn run(self, state: AppState) -> anyhow::Result<()> {
+        let pool = PgPool::from_ref(&state);
+        match self {
+            Job::TranscribeDiary { diary_id } => {
+                let object_store = Arc::<dyn ObjectStore>::from_ref(&state);
+                let transcriber = Arc::<ChunkedTranscriber>::from_ref(&state);
+                transcribe_diary(&pool, object_store.as_ref(), transcriber.as_ref(), diary_id).await
+            }
+            Job::SummarizeDiary { diary_id } => {
+                let analyzer = Arc::<dyn TextAnalyzer>::from_ref(&state);
+                summarize_diary(&pool, analyzer.as_ref(), diary_id).await
+            }
+            Job::EmbedDiary { diary_id } => {
+                let embedder = Arc::<dyn Embedder>::from_ref(&state);
+                embed_diary(&pool, embedder.as_ref(), diary_id).await
+            }
+            Job::GenerateReport {
+                user_id,
+                period,
+                period_start,
+            } => {
+                let analyzer = Arc::<dyn TextAnalyzer>::from_ref(&state);
+                refresh_report(&pool, analyzer.as_ref(), user_id, period, period_start).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Called once the job is dead-lettered, to surface the failure.
+    pub async fn on_dead(&self, state: &AppState, error: &str) -> anyhow::Result<()> {
+        let pool = PgPool::from_ref(state);
+        match self {
+            Job::TranscribeDiary { diary_id } | Job::SummarizeDiary { diary_id } => {
+                fail_diary(&pool, *diary_id, error).await
+            }
+            // the diary is complete without it, it just won't show up as similar
+            Job::EmbedDiary { .. } => Ok(()),
+            // written on demand when the report is requested
+            Job::GenerateReport { .. } => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        diary::{delete_trashed_diary, get_expired_trashed_diaries},
+        user_deco::delete_user_decos_of_diary,
+    },
+    storage::{diary::delete_diary_audio, store::ObjectStore},
+    AppState,
+};
+
+// diaries purged per sweep, the rest waits for the next one
+const PURGE_BATCH_SIZE: i64 = 100;
+
+#[derive(Clone, Debug)]
+pub struct PurgeConfig {
+    pub retention: Duration,
+    pub interval: Duration,
+}
+
+impl PurgeConfig {
+    pub fn from_env() -> Self {
+        let env_or = |key: &str, default: u64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            retention: Duration::from_secs(env_or("DIARY_TRASH_RETENTION_DAYS", 30) * 24 * 3600),
+            interval: Duration::from_secs(env_or("DIARY_PURGE_INTERVAL_SECS", 3600)),
+        }
+    }
+}
+
+/// Permanently removes a trashed diary: its decorations, the row and the
+/// recording. The rows are only deleted if the recording could be deleted too.
+/// Returns false if the diary is not in the trash.
+pub async fn purge_diary(
+    pool: &PgPool,
+    object_store: &dyn ObjectStore,
+    user_id: Uuid,
+    diary_id: i64,
+) -> anyhow::Result<bool> {
+    let mut tx = pool.begin().await?;
+    delete_user_decos_of_diary(&mut tx, user_id, diary_id).await?;
+    let Some(audio_key) = delete_trashed_diary(&mut tx, user_id, diary_id).await? else {
+        return Ok(false);
+    };
+    if let Some(audio_key) = audio_key {
+        delete_diary_audio(object_store, &audio_key).await?;
+    }
+    tx.commit().await?;
+    Ok(true)
+}
+
+pub fn spawn_trash_purger(state: AppState, config: PurgeConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = purge_expired(&state, config.retention).await {
+                tracing::error!("Failed to purge trashed diaries: {:#}", e);
+            }
+        }
+    });
+}
+
+async fn purge_expired(state: &AppState, retention: Duration) -> anyhow::Result<()> {
+    let expired =
+        get_expired_trashed_diaries(&state.pool, retention.as_secs_f64(), PURGE_BATCH_SIZE).await?;
+    for diary in expired {
+        match purge_diary(
+            &state.pool,
+            state.object_store.as_ref(),
+            diary.user_id,
+            diary.id,
+        )
+        .await
+        {
+            Ok(_) => tracing::debug!("Purged diary {}", diary.id),
+            // retried on the next sweep
+            Err(e) => tracing::error!("Failed to purge diary {}: {:#}", diary.id, e),
+        }
+    }
+    Ok(())
+}
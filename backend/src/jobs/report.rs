@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::{
+    analysis::{analyzer::TextAnalyzer, report::ReportEntry},
+    db::{
+        diary::get_diaries_between,
+        job::enqueue_job,
+        report::{
+            get_report, get_users_due_report, upsert_report, EmotionCounts, Report, ReportParams,
+            ReportPeriod,
+        },
+    },
+    error::{AppError, AppResult},
+    jobs::kind::Job,
+    AppState,
+};
+
+#[derive(Clone, Debug)]
+pub struct ReportConfig {
+    pub interval: Duration,
+}
+
+impl ReportConfig {
+    pub fn from_env() -> Self {
+        let interval = std::env::var("REPORT_SCHEDULE_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(3600);
+        Self {
+            interval: Duration::from_secs(interval),
+        }
+    }
+}
+
+fn period_label(period: ReportPeriod, start: Date) -> String {
+    match period {
+        ReportPeriod::Week => format!("the week of {}", start),
+        ReportPeriod::Month => format!("{} {}", start.month(), start.year()),
+    }
+}
+
+/// Returns the user's report of the period starting at `start`, writing it
+/// first if there is none yet or the summarized diaries of the period changed
+/// since. `None` if the period has no summarized diary.
+pub async fn refresh_report(
+    pool: &PgPool,
+    analyzer: &dyn TextAnalyzer,
+    user_id: Uuid,
+    period: ReportPeriod,
+    start: Date,
+) -> AppResult<Option<Report>> {
+    let start = period.start_of(start);
+    let diaries = get_diaries_between(pool, user_id, start, period.end(start))
+        .await?
+        .into_iter()
+        .filter(|diary| diary.summary.is_some())
+        .collect::<Vec<_>>();
+    if diaries.is_empty() {
+        return Ok(None);
+    }
+
+    let mut hasher = Sha256::new();
+    for diary in &diaries {
+        hasher.update(format!(
+            "{}\n{:?}\n{}\n",
+            diary.id,
+            diary.emotion,
+            diary.summary.as_deref().unwrap_or_default()
+        ));
+    }
+    let fingerprint = hex::encode(hasher.finalize());
+    if let Some(report) = get_report(pool, user_id, period, start).await? {
+        if report.fingerprint == fingerprint {
+            return Ok(Some(report));
+        }
+    }
+
+    let mut emotion_counts = EmotionCounts::new();
+    for emotion in diaries.iter().filter_map(|diary| diary.emotion) {
+        *emotion_counts.entry(emotion).or_default() += 1;
+    }
+    let entries = diaries
+        .iter()
+        .map(|diary| ReportEntry {
+            local_date: diary.local_date,
+            emotion: diary.emotion,
+            summary: diary.summary.clone().unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+    let digest = analyzer
+        .reflect(&period_label(period, start), &entries)
+        .await
+        .map_err(|e| AppError::upstream(format!("{:#}", e)))?;
+
+    let mut conn = pool.acquire().await?;
+    let report = upsert_report(
+        &mut conn,
+        user_id,
+        ReportParams {
+            period,
+            period_start: start,
+            diary_count: diaries.len() as i32,
+            emotion_counts,
+            digest,
+            fingerprint,
+        },
+    )
+    .await?;
+    Ok(Some(report))
+}
+
+/// Queues the reports of the last finished week and month of every user who
+/// wrote in them. A period counts as finished a day after it ends, so users
+/// behind UTC are done with it too.
+pub fn spawn_report_scheduler(state: AppState, config: ReportConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = enqueue_due_reports(&state.pool).await {
+                tracing::error!("Failed to schedule reports: {:#}", e);
+            }
+        }
+    });
+}
+
+async fn enqueue_due_reports(pool: &PgPool) -> anyhow::Result<()> {
+    let reference = OffsetDateTime::now_utc().date() - time::Duration::days(1);
+    for period in [ReportPeriod::Week, ReportPeriod::Month] {
+        let start = period.previous(period.start_of(reference));
+        let user_ids = get_users_due_report(pool, period, start, period.end(start)).await?;
+        if user_ids.is_empty() {
+            continue;
+        }
+        let mut tx = pool.begin().await?;
+        for user_id in &user_ids {
+            enqueue_job(
+                &mut tx,
+                Job::GenerateReport {
+                    user_id: *user_id,
+                    period,
+                    period_start: start,
+                },
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        tracing::debug!(
+            "Queued {} {:?} reports starting {}",
+            user_ids.len(),
+            period,
+            start
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    db::job::{claim_job, complete_job, dead_letter_job, extend_job_lease, retry_job, JobRow},
+    AppState,
+};
+
+#[derive(Clone, Debug)]
+pub struct WorkerConfig {
+    pub workers: usize,
+    // a running job whose lease is not renewed in time is considered abandoned and
+    // reclaimed; its worker renews it every third of the lease while it runs
+    pub lease: Duration,
+    pub poll_interval: Duration,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl WorkerConfig {
+    pub fn from_env() -> Self {
+        let env_or = |key: &str, default: u64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            workers: env_or("JOB_WORKERS", 2) as usize,
+            lease: Duration::from_secs(env_or("JOB_LEASE_SECS", 600).max(1)),
+            poll_interval: Duration::from_millis(env_or("JOB_POLL_INTERVAL_MS", 1000)),
+            base_backoff: Duration::from_secs(env_or("JOB_BASE_BACKOFF_SECS", 10)),
+            max_backoff: Duration::from_secs(env_or("JOB_MAX_BACKOFF_SECS", 3600)),
+        }
+    }
+
+    fn backoff(&self, attempts: i32) -> Duration {
+        let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+        self.base_backoff
+            .saturating_mul(2u32.pow(exponent))
+            .min(self.max_backoff)
+    }
+}
+
+/// Renews the lease of a running job every third of `lease`, so jobs that run
+/// longer than the lease are not reclaimed while their worker is alive.
+/// Returns only once the job was reclaimed by another worker.
+pub async fn hold_lease(pool: &PgPool, job_id: i64, worker_id: &str, lease: Duration) {
+    let mut renewals = tokio::time::interval(lease / 3);
+    // the first tick is immediate, the lease was just taken
+    renewals.tick().await;
+    loop {
+        renewals.tick().await;
+        match extend_job_lease(pool, job_id, worker_id).await {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(e) => tracing::error!("Failed to renew the lease of job {}: {}", job_id, e),
+        }
+    }
+}
+
+pub fn spawn_workers(state: AppState, config: WorkerConfig) {
+    for _ in 0..config.workers {
+        let worker = Worker {
+            id: Uuid::new_v4().to_string(),
+            state: state.clone(),
+            config: config.clone(),
+        };
+        tokio::spawn(worker.run());
+    }
+}
+
+struct Worker {
+    id: String,
+    state: AppState,
+    config: WorkerConfig,
+}
+
+impl Worker {
+    async fn run(self) {
+        tracing::debug!("Job worker {} started", self.id);
+        loop {
+            match claim_job(&self.state.pool, &self.id, self.config.lease.as_secs_f64()).await {
+                Ok(Some(job)) => self.process(job).await,
+                Ok(None) => tokio::time::sleep(self.config.poll_interval).await,
+                Err(e) => {
+                    tracing::error!("Failed to claim job: {}", e);
+                    tokio::time::sleep(self.config.poll_interval).await;
+                }
+            }
+        }
+    }
+
+    async fn process(&self, job: JobRow) {
+        let pool = &self.state.pool;
+
+        // reclaimed after its worker died on the last allowed attempt
+        if job.attempts > job.max_attempts {
+            let error = job
+                .last_error
+                .clone()
+                .unwrap_or_else(|| "worker lost while running the last attempt".to_string());
+            self.dead_letter(&job, &error).await;
+            return;
+        }
+
+        tracing::debug!(
+            "Running job {} ({}), attempt {}/{}",
+            job.id,
+            job.kind,
+            job.attempts,
+            job.max_attempts
+        );
+        // run on its own task so a panicking job is recorded as a failure
+        let payload = job.payload.0.clone();
+        let state = self.state.clone();
+        let mut running = tokio::spawn(payload.run(state));
+        let outcome = tokio::select! {
+            outcome = &mut running => outcome,
+            _ = hold_lease(pool, job.id, &self.id, self.config.lease) => {
+                // it runs on the worker that reclaimed it now
+                running.abort();
+                tracing::warn!("Job {} ({}) was reclaimed while running", job.id, job.kind);
+                return;
+            }
+        };
+        let error = match outcome {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(format!("{:#}", e)),
+            Err(e) => Some(format!("job panicked: {}", e)),
+        };
+
+        let res = match error {
+            None => complete_job(pool, job.id, &self.id).await,
+            Some(error) if job.attempts >= job.max_attempts => {
+                tracing::error!(
+                    "Job {} ({}) failed permanently: {}",
+                    job.id,
+                    job.kind,
+                    error
+                );
+                // also marks the diary failed, see `Job::on_dead`
+                self.dead_letter(&job, &error).await;
+                Ok(())
+            }
+            Some(error) => {
+                let backoff = self.config.backoff(job.attempts);
+                tracing::warn!(
+                    "Job {} ({}) failed, retrying in {:?}: {}",
+                    job.id,
+                    job.kind,
+                    backoff,
+                    error
+                );
+                retry_job(pool, job.id, &self.id, &error, backoff.as_secs_f64()).await
+            }
+        };
+        if let Err(e) = res {
+            tracing::error!("Failed to record result of job {}: {}", job.id, e);
+        }
+    }
+
+    async fn dead_letter(&self, job: &JobRow, error: &str) {
+        if let Err(e) = dead_letter_job(&self.state.pool, job.id, &self.id, error).await {
+            tracing::error!("Failed to dead-letter job {}: {}", job.id, e);
+            return;
+        }
+        if let Err(e) = job.payload.0.on_dead(&self.state, error).await {
+            tracing::error!("Failed to handle dead job {}: {:#}", job.id, e);
+        }
+    }
+}
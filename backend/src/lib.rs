@@ -1,34 +1,80 @@
 use std::sync::Arc;
 
+use analysis::analyzer::{analyzer_from_env, TextAnalyzer};
+use audio::pipeline::AudioPipeline;
+use auth::jwt::JwtVerifier;
 use axum::extract::FromRef;
 use db::conn::initialize_conn_pool;
+use embedding::embedder::{embedder_from_env, Embedder};
+use events::DiaryEvents;
 use openai::client::OpenAIClient;
-use storage::client::SupabaseClient;
+use room::{grid::RoomGrid, history::RoomHistory};
+use storage::{
+    links::LinkSigner,
+    store::{object_store_from_env, ObjectStore},
+};
+use transcription::{
+    chunked::{ChunkConfig, ChunkedTranscriber},
+    transcriber::transcriber_from_env,
+};
 
+pub mod analysis;
+pub mod audio;
+pub mod auth;
 pub mod db;
+pub mod embedding;
+pub mod error;
+pub mod events;
 pub mod handlers;
+pub mod jobs;
 pub mod openai;
+pub mod room;
 pub mod storage;
+pub mod transcription;
 pub mod utils;
 
 #[derive(Clone)]
 pub struct AppState {
     pool: sqlx::PgPool,
-    storage_client: SupabaseClient,
+    object_store: Arc<dyn ObjectStore>,
+    link_signer: Arc<LinkSigner>,
+    audio_pipeline: Arc<AudioPipeline>,
     openai_client: Arc<OpenAIClient>,
+    transcriber: Arc<ChunkedTranscriber>,
+    analyzer: Arc<dyn TextAnalyzer>,
+    embedder: Arc<dyn Embedder>,
+    jwt_verifier: Arc<JwtVerifier>,
+    diary_events: DiaryEvents,
+    room_grid: RoomGrid,
+    room_history: RoomHistory,
 }
 
 impl AppState {
     pub async fn default() -> Self {
+        let pool = initialize_conn_pool().await;
+        let diary_events = DiaryEvents::new();
+        diary_events.spawn_listener(pool.clone());
+        let openai_client = Arc::new(OpenAIClient::new());
+        let object_store = object_store_from_env().expect("Failed to initialize object store");
         Self {
-            pool: initialize_conn_pool().await,
-            storage_client: SupabaseClient::new(
-                std::env::var("SUPABASE_URL").unwrap(),
-                std::env::var("SUPABASE_KEY").unwrap(),
-                std::env::var("SUPABASE_AUDIO_BUCKET").unwrap(),
-                std::env::var("SUPABASE_MODEL_BUCKET").unwrap(),
+            pool,
+            link_signer: Arc::new(LinkSigner::from_env(object_store.clone())),
+            object_store,
+            audio_pipeline: Arc::new(AudioPipeline::from_env()),
+            transcriber: Arc::new(ChunkedTranscriber::new(
+                transcriber_from_env(openai_client.clone())
+                    .expect("Failed to initialize transcriber"),
+                ChunkConfig::from_env(),
+            )),
+            openai_client,
+            analyzer: analyzer_from_env().expect("Failed to initialize text analyzer"),
+            embedder: embedder_from_env().expect("Failed to initialize embedder"),
+            jwt_verifier: Arc::new(
+                JwtVerifier::from_env().expect("Failed to initialize JWT verifier"),
             ),
-            openai_client: Arc::new(OpenAIClient::new()),
+            diary_events,
+            room_grid: RoomGrid::from_env(),
+            room_history: RoomHistory::from_env(),
         }
     }
 }
@@ -39,9 +85,21 @@ impl FromRef<AppState> for sqlx::PgPool {
     }
 }
 
-impl FromRef<AppState> for SupabaseClient {
-    fn from_ref(state: &AppState) -> SupabaseClient {
-        state.storage_client.clone()
+impl FromRef<AppState> for Arc<dyn ObjectStore> {
+    fn from_ref(state: &AppState) -> Arc<dyn ObjectStore> {
+        state.object_store.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<LinkSigner> {
+    fn from_ref(state: &AppState) -> Arc<LinkSigner> {
+        state.link_signer.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<AudioPipeline> {
+    fn from_ref(state: &AppState) -> Arc<AudioPipeline> {
+        state.audio_pipeline.clone()
     }
 }
 
@@ -50,3 +108,45 @@ impl FromRef<AppState> for Arc<OpenAIClient> {
         state.openai_client.clone()
     }
 }
+
+impl FromRef<AppState> for Arc<JwtVerifier> {
+    fn from_ref(state: &AppState) -> Arc<JwtVerifier> {
+        state.jwt_verifier.clone()
+    }
+}
+
+impl FromRef<AppState> for DiaryEvents {
+    fn from_ref(state: &AppState) -> DiaryEvents {
+        state.diary_events.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<ChunkedTranscriber> {
+    fn from_ref(state: &AppState) -> Arc<ChunkedTranscriber> {
+        state.transcriber.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn TextAnalyzer> {
+    fn from_ref(state: &AppState) -> Arc<dyn TextAnalyzer> {
+        state.analyzer.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn Embedder> {
+    fn from_ref(state: &AppState) -> Arc<dyn Embedder> {
+        state.embedder.clone()
+    }
+}
+
+impl FromRef<AppState> for RoomGrid {
+    fn from_ref(state: &AppState) -> RoomGrid {
+        state.room_grid
+    }
+}
+
+impl FromRef<AppState> for RoomHistory {
+    fn from_ref(state: &AppState) -> RoomHistory {
+        state.room_history
+    }
+}
@@ -1,16 +1,34 @@
 use axum::{
     extract::DefaultBodyLimit,
-    routing::{get, post},
+    middleware,
+    routing::{get, post, put},
     Router,
 };
 use recordiary::{
     handlers::{
+        ask::ask_diary,
         calendar::get_calendar,
         deco::{create_deco, get_available_decos, get_deco},
-        diary::{create_diary, get_diary},
+        diary::{
+            create_diary, delete_diary, get_diary, get_diary_status, get_diary_transcript,
+            get_trash, patch_diary, purge_trashed_diary, restore_diary, stream_diary_status,
+        },
         health::healthcheck,
-        room::{create_user_deco, get_room, update_user_deco},
+        report::{get_monthly_report, get_weekly_report},
+        room::{
+            create_user_deco, get_room, get_room_history, get_room_snapshot, restore_room_layout,
+            save_room_layout, update_room_theme, update_user_deco,
+        },
+        search::{get_similar_diaries, search_diary},
+        share::{create_share, get_shared_room, get_shares, revoke_share},
+        storage::get_signed_object,
     },
+    jobs::{
+        purge::{spawn_trash_purger, PurgeConfig},
+        report::{spawn_report_scheduler, ReportConfig},
+        worker::{spawn_workers, WorkerConfig},
+    },
+    utils::request_id::request_id,
     AppState,
 };
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -30,18 +48,56 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    let state = AppState::default().await;
+    // uploads are spooled to disk, so this only bounds disk usage per request
+    let upload_limit_mb = std::env::var("UPLOAD_LIMIT_MB")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(20);
+    spawn_workers(state.clone(), WorkerConfig::from_env());
+    spawn_trash_purger(state.clone(), PurgeConfig::from_env());
+    spawn_report_scheduler(state.clone(), ReportConfig::from_env());
+
     let app = Router::new()
         .route("/", get(healthcheck))
         .route("/calendar", get(get_calendar))
-        .route("/diary", post(create_diary).get(get_diary))
+        .route(
+            "/diary",
+            post(create_diary)
+                .get(get_diary)
+                .patch(patch_diary)
+                .delete(delete_diary),
+        )
+        .route("/diary/status", get(get_diary_status))
+        .route("/diary/trash", get(get_trash).delete(purge_trashed_diary))
+        .route("/diary/trash/restore", post(restore_diary))
+        .route("/diary/status/stream", get(stream_diary_status))
+        .route("/diary/transcript", get(get_diary_transcript))
+        .route("/diary/search", get(search_diary))
+        .route("/diary/similar", get(get_similar_diaries))
+        .route("/diary/ask", post(ask_diary))
+        .route("/report", get(get_monthly_report))
+        .route("/report/weekly", get(get_weekly_report))
         .route("/deco", get(get_deco).post(create_deco))
         .route("/deco/available", get(get_available_decos))
         .route(
             "/room",
             get(get_room).post(create_user_deco).put(update_user_deco),
         )
-        .with_state(AppState::default().await)
-        .layer(DefaultBodyLimit::max(20 * 1024 * 1024 /* 20mb */)); // about 1 minute per mb
+        .route("/room/layout", put(save_room_layout))
+        .route("/room/theme", put(update_room_theme))
+        .route(
+            "/room/share",
+            get(get_shares).post(create_share).delete(revoke_share),
+        )
+        .route("/shared/room/:token", get(get_shared_room))
+        .route("/room/history", get(get_room_history))
+        .route("/room/history/layout", get(get_room_snapshot))
+        .route("/room/history/restore", post(restore_room_layout))
+        .route("/storage/:bucket/*key", get(get_signed_object))
+        .with_state(state)
+        .layer(DefaultBodyLimit::max(upload_limit_mb * 1024 * 1024)) // about 1 minute per mb
+        .layer(middleware::from_fn(request_id));
 
     // run our app with hyper, listening globally on port 3000
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", env!("PORT")))
@@ -1,2 +1,4 @@
+pub mod chat;
 pub mod client;
 pub mod diary;
+pub mod embedding;
@@ -0,0 +1,152 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use itertools::Itertools;
+use openai_api_rs::v1::chat_completion::{
+    ChatCompletionMessage, ChatCompletionRequest, Content, MessageRole,
+};
+use serde_json::json;
+
+use crate::analysis::{
+    analyzer::TextAnalyzer,
+    ask::{parse_diary_answer, render_excerpts, DiaryAnswer, DiaryExcerpt},
+    config::{render_prompt, AnalyzerConfig},
+    emotion::{parse_emotion_analysis, Emotion, EmotionAnalysis},
+    report::{parse_reflection, render_entries, ReflectionDigest, ReportEntry},
+};
+
+/// Chat-completion analyzer for OpenAI or any server speaking its api
+/// (llama.cpp, Ollama, vLLM, ...).
+pub struct ChatAnalyzer {
+    openai: openai_api_rs::v1::api::OpenAIClient,
+    config: AnalyzerConfig,
+}
+
+impl ChatAnalyzer {
+    pub fn openai(config: AnalyzerConfig) -> anyhow::Result<Self> {
+        let openai = openai_api_rs::v1::api::OpenAIClient::builder()
+            .with_api_key(std::env::var("OPENAI_API_KEY")?)
+            .build()
+            .map_err(|e| anyhow!("Failed to create OpenAI client: {}", e))?;
+        Ok(Self { openai, config })
+    }
+
+    pub fn compatible(
+        base_url: String,
+        api_key: String,
+        config: AnalyzerConfig,
+    ) -> anyhow::Result<Self> {
+        let openai = openai_api_rs::v1::api::OpenAIClient::builder()
+            .with_endpoint(base_url)
+            .with_api_key(api_key)
+            .build()
+            .map_err(|e| anyhow!("Failed to create OpenAI-compatible client: {}", e))?;
+        Ok(Self { openai, config })
+    }
+
+    async fn complete(
+        &self,
+        prompt: String,
+        json_mode: bool,
+        max_tokens: i64,
+    ) -> anyhow::Result<Option<String>> {
+        let request = ChatCompletionRequest {
+            model: self.config.model.clone(),
+            messages: vec![ChatCompletionMessage {
+                role: MessageRole::system,
+                content: Content::Text(prompt),
+                name: None,
+                tool_call_id: None,
+                tool_calls: None,
+            }],
+            temperature: Some(self.config.temperature),
+            top_p: None,
+            n: None,
+            response_format: json_mode.then(|| json!({ "type": "json_object" })),
+            stream: None,
+            stop: None,
+            max_tokens: Some(max_tokens),
+            presence_penalty: None,
+            frequency_penalty: None,
+            logit_bias: None,
+            user: None,
+            seed: None,
+            tools: None,
+            parallel_tool_calls: None,
+            tool_choice: None,
+        };
+        let resp = self.openai.chat_completion(request).await?;
+        Ok(resp
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content))
+    }
+}
+
+#[async_trait]
+impl TextAnalyzer for ChatAnalyzer {
+    async fn summarize(&self, content: &str) -> anyhow::Result<String> {
+        let prompt = render_prompt(&self.config.summary_prompt, content);
+        Ok(self
+            .complete(prompt, false, self.config.max_tokens)
+            .await?
+            .unwrap_or_default())
+    }
+
+    async fn sentiment(&self, content: &str) -> anyhow::Result<EmotionAnalysis> {
+        let labels = Emotion::ALL.iter().map(|e| e.label()).join(", ");
+        let template = self.config.sentiment_prompt.replace("{labels}", &labels);
+        let prompt = render_prompt(&template, content);
+
+        let mut last_error = anyhow!("No sentiment attempts were made");
+        for attempt in 1..=self.config.sentiment_attempts.max(1) {
+            let raw = self
+                .complete(prompt.clone(), true, self.config.max_tokens)
+                .await?
+                .unwrap_or_default();
+            match parse_emotion_analysis(&raw) {
+                Ok(analysis) => return Ok(analysis),
+                Err(e) => {
+                    tracing::warn!("Invalid sentiment response (attempt {}): {}", attempt, e);
+                    last_error = e.context(format!("invalid sentiment response: {}", raw));
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    async fn answer(
+        &self,
+        question: &str,
+        excerpts: &[DiaryExcerpt],
+    ) -> anyhow::Result<DiaryAnswer> {
+        let prompt = self
+            .config
+            .ask_prompt
+            .replace("{question}", question)
+            .replace("{diaries}", &render_excerpts(excerpts));
+        let raw = self
+            .complete(prompt, true, self.config.ask_max_tokens)
+            .await?
+            .unwrap_or_default();
+        parse_diary_answer(&raw, excerpts)
+            .map_err(|e| e.context(format!("invalid answer: {}", raw)))
+    }
+
+    async fn reflect(
+        &self,
+        period: &str,
+        entries: &[ReportEntry],
+    ) -> anyhow::Result<ReflectionDigest> {
+        let prompt = self
+            .config
+            .report_prompt
+            .replace("{period}", period)
+            .replace("{diaries}", &render_entries(entries));
+        let raw = self
+            .complete(prompt, true, self.config.report_max_tokens)
+            .await?
+            .unwrap_or_default();
+        parse_reflection(&raw).map_err(|e| e.context(format!("invalid reflection: {}", raw)))
+    }
+}
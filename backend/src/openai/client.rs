@@ -1,129 +1,54 @@
-use std::{fs::File, io::Write};
+use std::path::Path;
 
-use openai_api_rs::v1::{
-    audio::AudioTranscriptionRequest,
-    chat_completion::{ChatCompletionMessage, ChatCompletionRequest, Content, MessageRole},
+use anyhow::anyhow;
+use async_trait::async_trait;
+use reqwest::multipart::Form;
+use serde::Deserialize;
+
+use crate::transcription::{
+    transcriber::Transcriber,
+    transcript::{Transcript, TranscriptSegment},
 };
 
-pub enum Emotion {
-    Anger,
-    Sadness,
-    Happiness,
-    Neutral,
-}
+// the audio endpoints are called directly, the openai crate only returns the text
+const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
 
 pub struct OpenAIClient {
-    openai: openai_api_rs::v1::api::OpenAIClient,
+    client: reqwest::Client,
+    api_key: String,
+    api_base: String,
+    transcription_model: String,
+    transcription_language: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct VerboseTranscription {
+    text: String,
+    #[serde(default)]
+    segments: Vec<VerboseSegment>,
+}
+
+#[derive(Deserialize, Debug)]
+struct VerboseSegment {
+    // seconds
+    start: f64,
+    end: f64,
+    text: String,
 }
 
 impl OpenAIClient {
     pub fn new() -> Self {
         Self {
-            openai: openai_api_rs::v1::api::OpenAIClient::builder()
-                .with_api_key(std::env::var("OPENAI_API_KEY").unwrap())
-                .build()
-                .expect("Failed to create OpenAI client"),
+            client: reqwest::Client::new(),
+            api_key: std::env::var("OPENAI_API_KEY").unwrap(),
+            api_base: std::env::var("OPENAI_API_BASE")
+                .unwrap_or_else(|_| OPENAI_API_BASE.to_string()),
+            transcription_model: std::env::var("OPENAI_TRANSCRIPTION_MODEL")
+                .unwrap_or_else(|_| "whisper-1".to_string()),
+            transcription_language: std::env::var("TRANSCRIBE_LANGUAGE")
+                .unwrap_or_else(|_| "ko".to_string()),
         }
     }
-
-    pub async fn transcribe(
-        &self,
-        audio_title: &str,
-        audio_content: &[u8],
-    ) -> anyhow::Result<String> {
-        let tmp_dir = tempfile::tempdir()?; // the directory will be dropped with the lifetime
-        let tmp_path = tmp_dir.path().join(audio_title);
-        let mut audio_file = File::create(tmp_path.clone())?;
-
-        audio_file.write_all(audio_content)?;
-        audio_file.flush()?;
-        let request = AudioTranscriptionRequest {
-            file: tmp_path.to_string_lossy().to_string(),
-            model: "whisper-1".to_string(),
-            prompt: None,
-            response_format: Some("json".to_string()),
-            temperature: None,
-            language: Some("ko".to_string()),
-        };
-        let resp = self.openai.audio_transcription(request).await?;
-        Ok(resp.text)
-    }
-
-    pub async fn summarize(&self, content: &str) -> anyhow::Result<String> {
-        let request = ChatCompletionRequest {
-            model: "gpt-3.5-turbo".to_string(),
-            messages: vec![ChatCompletionMessage {
-                role: MessageRole::system,
-                content: Content::Text(
-                    "Summarize the following text in korean: ".to_string() + content,
-                ),
-                name: None,
-                tool_call_id: None,
-                tool_calls: None,
-            }],
-            temperature: Some(0.0),
-            top_p: None,
-            n: None,
-            response_format: None,
-            stream: None,
-            stop: None,
-            max_tokens: Some(50),
-            presence_penalty: None,
-            frequency_penalty: None,
-            logit_bias: None,
-            user: None,
-            seed: None,
-            tools: None,
-            parallel_tool_calls: None,
-            tool_choice: None,
-        };
-        let resp = self.openai.chat_completion(request).await?;
-        Ok(resp.choices[0]
-            .message
-            .content
-            .clone()
-            .unwrap_or("".to_string())
-            .to_string())
-    }
-
-    pub async fn sentiment(&self, content: &str) -> anyhow::Result<String> {
-        let base_prompt = "Analyze the sentiment of the following korean text. 
-        Only respond with one of the following choices: anger, sadness, happiness, neutral.
-        Target text: ";
-        let request = ChatCompletionRequest {
-            model: "gpt-3.5-turbo".to_string(),
-            messages: vec![ChatCompletionMessage {
-                role: MessageRole::system,
-                content: Content::Text(base_prompt.to_string() + content),
-                name: None,
-                tool_call_id: None,
-                tool_calls: None,
-            }],
-            temperature: Some(0.0),
-            top_p: None,
-            n: None,
-            response_format: None,
-            stream: None,
-            stop: None,
-            max_tokens: Some(50),
-            presence_penalty: None,
-            frequency_penalty: None,
-            logit_bias: None,
-            user: None,
-            seed: None,
-            tools: None,
-            parallel_tool_calls: None,
-            tool_choice: None,
-        };
-        let resp = self.openai.chat_completion(request).await?;
-        let raw_emotion = resp.choices[0]
-            .message
-            .content
-            .clone()
-            .unwrap_or("neutral".to_string())
-            .to_string();
-        Ok(raw_emotion)
-    }
 }
 
 impl Default for OpenAIClient {
@@ -131,3 +56,49 @@ impl Default for OpenAIClient {
         Self::new()
     }
 }
+
+#[async_trait]
+impl Transcriber for OpenAIClient {
+    async fn transcribe(&self, audio: &Path, prompt: Option<&str>) -> anyhow::Result<Transcript> {
+        let mut form = Form::new()
+            .file("file", audio)
+            .await?
+            .text("model", self.transcription_model.clone())
+            .text("language", self.transcription_language.clone())
+            .text("response_format", "verbose_json");
+        if let Some(prompt) = prompt {
+            form = form.text("prompt", prompt.to_string());
+        }
+
+        let resp = self
+            .client
+            .post(format!("{}/audio/transcriptions", self.api_base))
+            .bearer_auth(&self.api_key)
+            .multipart(form)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            return Err(anyhow!(
+                "Transcription failed with {}: {}",
+                status,
+                resp.text().await.unwrap_or_default()
+            ));
+        }
+
+        let transcription = resp.json::<VerboseTranscription>().await?;
+        Ok(Transcript {
+            text: transcription.text.trim().to_string(),
+            segments: transcription
+                .segments
+                .into_iter()
+                .map(|segment| TranscriptSegment {
+                    start_ms: (segment.start * 1000.0).round() as i32,
+                    end_ms: (segment.end * 1000.0).round() as i32,
+                    text: segment.text.trim().to_string(),
+                    speaker: None,
+                })
+                .collect(),
+        })
+    }
+}
@@ -1,22 +1,90 @@
-use std::sync::Arc;
-
+use anyhow::anyhow;
 use sqlx::PgPool;
 
-use crate::{db::diary::update_diary, utils::sqlx::get_pg_tx};
+use crate::{
+    analysis::analyzer::TextAnalyzer,
+    db::{
+        diary::{get_diary_by_id, set_processing_status, update_diary, ProcessingStatus},
+        diary_embedding::upsert_diary_embedding,
+        diary_segment::replace_diary_segments,
+        job::enqueue_job,
+    },
+    embedding::embedder::Embedder,
+    jobs::kind::Job,
+    storage::{diary::download_diary_audio, store::ObjectStore},
+    transcription::chunked::ChunkedTranscriber,
+};
+
+// roughly the 8k token input limit of the embedding models, for korean text
+const EMBEDDING_MAX_CHARS: usize = 6000;
+
+pub async fn transcribe_diary(
+    pool: &PgPool,
+    object_store: &dyn ObjectStore,
+    transcriber: &ChunkedTranscriber,
+    diary_id: i64,
+) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    let Some(diary) = get_diary_by_id(&mut tx, diary_id).await? else {
+        tracing::warn!(
+            "Diary {} no longer exists, skipping transcription",
+            diary_id
+        );
+        return Ok(());
+    };
+    set_processing_status(&mut tx, diary_id, ProcessingStatus::Transcribing, None).await?;
+    tx.commit().await?;
+
+    let Some(audio_key) = diary.audio_key else {
+        return Err(anyhow!("Diary {} has no recording", diary_id));
+    };
+    // the recording is streamed to disk, named after its key to keep the extension
+    let tmp_dir = tempfile::tempdir()?;
+    let audio_path = tmp_dir.path().join(&audio_key);
+    download_diary_audio(object_store, &audio_key, &audio_path).await?;
+    let transcript = transcriber
+        .transcribe(&audio_path, diary.duration_ms)
+        .await?;
 
-use super::client::OpenAIClient;
+    // store the transcription and schedule the summary atomically
+    let mut tx = pool.begin().await?;
+    update_diary(
+        &mut tx,
+        diary_id,
+        None,
+        None,
+        Some(transcript.text),
+        None,
+        None,
+    )
+    .await?;
+    replace_diary_segments(&mut tx, diary_id, &transcript.segments).await?;
+    set_processing_status(&mut tx, diary_id, ProcessingStatus::Summarizing, None).await?;
+    enqueue_job(&mut tx, Job::SummarizeDiary { diary_id }).await?;
+    tx.commit().await?;
+    Ok(())
+}
 
 pub async fn summarize_diary(
-    pool: PgPool,
-    client: Arc<OpenAIClient>,
+    pool: &PgPool,
+    analyzer: &dyn TextAnalyzer,
     diary_id: i64,
-    transcription: String,
-) {
-    let mut tx = get_pg_tx(pool).await.unwrap();
+) -> anyhow::Result<()> {
+    let mut conn = pool.acquire().await?;
+    let Some(transcription) = get_diary_by_id(&mut conn, diary_id)
+        .await?
+        .and_then(|diary| diary.transcription)
+    else {
+        tracing::warn!("Diary {} has no transcription, skipping summary", diary_id);
+        return Ok(());
+    };
+    drop(conn);
+
+    let summary = analyzer.summarize(&transcription).await?;
+    let emotion = analyzer.sentiment(&transcription).await?;
 
-    let summary = client.summarize(&transcription).await.unwrap();
-    let emotion = client.sentiment(&transcription).await.unwrap();
-    let res = update_diary(
+    let mut tx = pool.begin().await?;
+    update_diary(
         &mut tx,
         diary_id,
         None,
@@ -25,17 +93,59 @@ pub async fn summarize_diary(
         Some(emotion),
         None,
     )
-    .await;
-
-    let tx_res = match res {
-        Ok(_) => tx.commit().await,
-        Err(e) => {
-            tracing::error!("Summarize error: {:?}", e);
-            tx.rollback().await
-        }
+    .await?;
+    set_processing_status(&mut tx, diary_id, ProcessingStatus::Complete, None).await?;
+    enqueue_job(&mut tx, Job::EmbedDiary { diary_id }).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// the text a diary is embedded from, cut to stay within the model's input limit
+fn embedding_input(summary: Option<&str>, transcription: &str) -> String {
+    let text = match summary {
+        Some(summary) => format!("{}\n\n{}", summary, transcription),
+        None => transcription.to_string(),
+    };
+    text.chars().take(EMBEDDING_MAX_CHARS).collect()
+}
+
+pub async fn embed_diary(
+    pool: &PgPool,
+    embedder: &dyn Embedder,
+    diary_id: i64,
+) -> anyhow::Result<()> {
+    let mut conn = pool.acquire().await?;
+    let Some(diary) = get_diary_by_id(&mut conn, diary_id).await? else {
+        tracing::warn!("Diary {} no longer exists, skipping embedding", diary_id);
+        return Ok(());
+    };
+    drop(conn);
+    let Some(transcription) = diary.transcription.as_deref() else {
+        tracing::warn!(
+            "Diary {} has no transcription, skipping embedding",
+            diary_id
+        );
+        return Ok(());
     };
 
-    if let Err(e) = tx_res {
-        tracing::error!("Summarize tx error: {:?}", e);
-    }
+    let embedding = embedder
+        .embed(&embedding_input(diary.summary.as_deref(), transcription))
+        .await?;
+    let mut conn = pool.acquire().await?;
+    upsert_diary_embedding(&mut conn, diary_id, embedder.model(), &embedding).await?;
+    Ok(())
+}
+
+/// Marks the diary as failed once its processing job is dead-lettered.
+pub async fn fail_diary(pool: &PgPool, diary_id: i64, error: &str) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    set_processing_status(
+        &mut tx,
+        diary_id,
+        ProcessingStatus::Failed,
+        Some(error.to_string()),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(())
 }
@@ -0,0 +1,79 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::embedding::embedder::Embedder;
+
+const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+
+/// `/embeddings` of OpenAI, or of any server speaking its api when
+/// `EMBEDDING_BASE_URL` is set.
+pub struct OpenAIEmbedder {
+    client: reqwest::Client,
+    api_key: String,
+    api_base: String,
+    model: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+}
+
+impl OpenAIEmbedder {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let api_base = std::env::var("EMBEDDING_BASE_URL")
+            .or_else(|_| std::env::var("OPENAI_API_BASE"))
+            .unwrap_or_else(|_| OPENAI_API_BASE.to_string());
+        let api_key = match std::env::var("EMBEDDING_API_KEY") {
+            Ok(key) => key,
+            Err(_) => std::env::var("OPENAI_API_KEY")
+                .map_err(|_| anyhow!("OPENAI_API_KEY must be set for the openai embedder"))?,
+        };
+        Ok(Self {
+            client: reqwest::Client::new(),
+            api_key,
+            api_base,
+            model: std::env::var("EMBEDDING_MODEL")
+                .unwrap_or_else(|_| "text-embedding-3-small".to_string()),
+        })
+    }
+}
+
+#[async_trait]
+impl Embedder for OpenAIEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn embed(&self, text: &str) -> anyhow::Result<Vec<f32>> {
+        let resp = self
+            .client
+            .post(format!("{}/embeddings", self.api_base))
+            .bearer_auth(&self.api_key)
+            .json(&json!({ "model": self.model, "input": text }))
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            return Err(anyhow!(
+                "Embedding failed with {}: {}",
+                status,
+                resp.text().await.unwrap_or_default()
+            ));
+        }
+        resp.json::<EmbeddingResponse>()
+            .await?
+            .data
+            .into_iter()
+            .next()
+            .map(|data| data.embedding)
+            .ok_or_else(|| anyhow!("Embedding response has no data"))
+    }
+}
@@ -0,0 +1,5 @@
+pub mod grid;
+pub mod history;
+pub mod layout;
+pub mod placement;
+pub mod theme;
@@ -0,0 +1,172 @@
+use serde::Serialize;
+
+use crate::utils::coordinates::Coordinates;
+
+/// Cells a deco occupies before rotation.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Footprint {
+    pub width: i64,
+    pub depth: i64,
+    pub height: i64,
+}
+
+/// `[min, max)` cells along each axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cells {
+    pub min: (i64, i64, i64),
+    pub max: (i64, i64, i64),
+}
+
+impl Cells {
+    /// The cells of a deco anchored at `coordinates`, its lowest corner.
+    /// Quarter turns (orientations 1 and 3) swap its width and depth.
+    pub fn of(coordinates: &Coordinates, footprint: Footprint) -> Self {
+        let (width, depth) = if coordinates.orientation % 2 == 1 {
+            (footprint.depth, footprint.width)
+        } else {
+            (footprint.width, footprint.depth)
+        };
+        Self {
+            min: (coordinates.x, coordinates.y, coordinates.z),
+            max: (
+                coordinates.x + width,
+                coordinates.y + footprint.height,
+                coordinates.z + depth,
+            ),
+        }
+    }
+
+    pub fn overlaps(&self, other: &Cells) -> bool {
+        self.min.0 < other.max.0
+            && other.min.0 < self.max.0
+            && self.min.1 < other.max.1
+            && other.min.1 < self.max.1
+            && self.min.2 < other.max.2
+            && other.min.2 < self.max.2
+    }
+}
+
+/// Size of a room in cells: x runs along its width, z along its depth and y
+/// up to its height.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoomGrid {
+    pub width: i64,
+    pub depth: i64,
+    pub height: i64,
+}
+
+impl RoomGrid {
+    pub fn from_env() -> Self {
+        let env_or = |key: &str, default: i64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(default)
+        };
+        Self {
+            width: env_or("ROOM_WIDTH", 10),
+            depth: env_or("ROOM_DEPTH", 10),
+            height: env_or("ROOM_HEIGHT", 10),
+        }
+    }
+
+    pub fn fits(&self, footprint: Footprint) -> bool {
+        // either way round on the floor
+        let flat = (footprint.width <= self.width && footprint.depth <= self.depth)
+            || (footprint.depth <= self.width && footprint.width <= self.depth);
+        flat && footprint.height <= self.height
+    }
+
+    /// Why a deco cannot be placed at `coordinates`, ignoring other decos.
+    pub fn check(&self, coordinates: &Coordinates, footprint: Footprint) -> Result<Cells, String> {
+        if !(0..4).contains(&coordinates.orientation) {
+            return Err(format!(
+                "Invalid orientation {}, expected 0, 1, 2 or 3",
+                coordinates.orientation
+            ));
+        }
+        let does_not_fit = || {
+            format!(
+                "A {}x{}x{} deco at ({}, {}, {}) does not fit in the {}x{}x{} room",
+                footprint.width,
+                footprint.depth,
+                footprint.height,
+                coordinates.x,
+                coordinates.y,
+                coordinates.z,
+                self.width,
+                self.depth,
+                self.height
+            )
+        };
+        // the anchor first, so the far corner cannot overflow
+        if !(0..self.width).contains(&coordinates.x)
+            || !(0..self.height).contains(&coordinates.y)
+            || !(0..self.depth).contains(&coordinates.z)
+        {
+            return Err(does_not_fit());
+        }
+        let cells = Cells::of(coordinates, footprint);
+        if cells.max.0 > self.width || cells.max.1 > self.height || cells.max.2 > self.depth {
+            return Err(does_not_fit());
+        }
+        Ok(cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: RoomGrid = RoomGrid {
+        width: 10,
+        depth: 8,
+        height: 5,
+    };
+    const SOFA: Footprint = Footprint {
+        width: 3,
+        depth: 1,
+        height: 1,
+    };
+
+    fn at(x: i64, y: i64, z: i64, orientation: i32) -> Coordinates {
+        Coordinates {
+            x,
+            y,
+            z,
+            orientation,
+        }
+    }
+
+    #[test]
+    fn check_keeps_decos_inside_the_room() {
+        assert!(GRID.check(&at(7, 0, 7, 0), SOFA).is_ok());
+        assert!(GRID.check(&at(8, 0, 0, 0), SOFA).is_err());
+        // turned a quarter, the sofa runs along z
+        assert!(GRID.check(&at(9, 0, 5, 1), SOFA).is_ok());
+        assert!(GRID.check(&at(9, 0, 6, 1), SOFA).is_err());
+        assert!(GRID.check(&at(-1, 0, 0, 0), SOFA).is_err());
+        assert!(GRID.check(&at(0, 0, 0, 4), SOFA).is_err());
+    }
+
+    #[test]
+    fn check_rejects_far_away_anchors_without_overflowing() {
+        for coordinates in [
+            at(i64::MAX, 0, 0, 0),
+            at(0, i64::MAX, 0, 0),
+            at(0, 0, i64::MAX, 1),
+            at(i64::MIN, 0, 0, 0),
+        ] {
+            assert!(GRID.check(&coordinates, SOFA).is_err());
+        }
+    }
+
+    #[test]
+    fn touching_decos_do_not_overlap() {
+        let sofa = Cells::of(&at(0, 0, 0, 0), SOFA);
+        assert!(!sofa.overlaps(&Cells::of(&at(3, 0, 0, 0), SOFA)));
+        assert!(!sofa.overlaps(&Cells::of(&at(0, 1, 0, 0), SOFA)));
+        assert!(sofa.overlaps(&Cells::of(&at(2, 0, 0, 1), SOFA)));
+    }
+}
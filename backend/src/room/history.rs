@@ -0,0 +1,79 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::db::{
+    room::{bump_layout_version, insert_layout_snapshot, lock_room, prune_layout_snapshots},
+    user_deco::get_room_decos,
+};
+
+use super::layout::LayoutPlacement;
+
+/// How many layout snapshots a user keeps, across all their rooms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoomHistory {
+    pub limit: i64,
+}
+
+impl RoomHistory {
+    pub fn from_env() -> Self {
+        Self {
+            limit: std::env::var("ROOM_HISTORY_LIMIT")
+                .ok()
+                .and_then(|v| v.parse::<i64>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(100),
+        }
+    }
+
+    /// Locks the room for a layout change and returns its version. The
+    /// layout from before the first change is recorded as version 0, so the
+    /// first change can be undone too.
+    pub async fn begin(
+        &self,
+        tx: &mut PgConnection,
+        user_id: Uuid,
+        year: i32,
+        month: u32,
+    ) -> sqlx::Result<i64> {
+        let version = lock_room(tx, user_id, year, month).await?;
+        if version == 0 {
+            self.record(tx, user_id, year, month, version).await?;
+        }
+        Ok(version)
+    }
+
+    /// Bumps the version of a room changed since `begin` and records its new
+    /// layout, dropping the user's oldest snapshots past the limit.
+    pub async fn commit(
+        &self,
+        tx: &mut PgConnection,
+        user_id: Uuid,
+        year: i32,
+        month: u32,
+    ) -> sqlx::Result<i64> {
+        let version = bump_layout_version(tx, user_id, year, month).await?;
+        self.record(tx, user_id, year, month, version).await?;
+        prune_layout_snapshots(tx, user_id, self.limit).await?;
+        Ok(version)
+    }
+
+    async fn record(
+        &self,
+        tx: &mut PgConnection,
+        user_id: Uuid,
+        year: i32,
+        month: u32,
+        version: i64,
+    ) -> sqlx::Result<()> {
+        let decos = get_room_decos(tx, user_id, year, month).await?;
+        insert_layout_snapshot(
+            tx,
+            user_id,
+            year,
+            month,
+            version,
+            &LayoutPlacement::of_room(&decos),
+        )
+        .await
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    db::user_deco::{update_user_deco, PlacedDeco},
+    error::{AppError, AppResult},
+    utils::coordinates::Coordinates,
+};
+
+use super::{
+    grid::RoomGrid,
+    placement::{conflict_error, PlacementConflict},
+};
+
+/// Where one user deco goes in a saved layout.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct LayoutPlacement {
+    pub diary_id: i64,
+    pub deco_id: i64,
+    pub coordinates: Coordinates,
+}
+
+impl LayoutPlacement {
+    /// The placements of the decos that are in the room.
+    pub fn of_room(decos: &[PlacedDeco]) -> Vec<Self> {
+        decos
+            .iter()
+            .filter_map(|deco| {
+                Some(Self {
+                    diary_id: deco.diary_id,
+                    deco_id: deco.deco_id,
+                    coordinates: deco.coordinates.as_ref()?.0.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Checks a full layout of a room against its user decos: each placement
+/// names a deco of the room once, fits in the room and is clear of the
+/// others. Returns where every deco of the room ends up, `None` for the
+/// ones the layout leaves out.
+pub fn check_layout<'a>(
+    grid: &RoomGrid,
+    decos: &'a [PlacedDeco],
+    placements: &[LayoutPlacement],
+) -> AppResult<Vec<(&'a PlacedDeco, Option<Coordinates>)>> {
+    let mut seen = HashSet::new();
+    let mut placed = Vec::with_capacity(placements.len());
+    for placement in placements {
+        let key = (placement.diary_id, placement.deco_id);
+        let deco = decos
+            .iter()
+            .find(|deco| (deco.diary_id, deco.deco_id) == key)
+            .ok_or_else(|| {
+                AppError::Validation(format!(
+                    "Deco {} of diary {} is not in this room",
+                    placement.deco_id, placement.diary_id
+                ))
+            })?;
+        if !seen.insert(key) {
+            return Err(AppError::Validation(format!(
+                "{} of diary {} is placed more than once",
+                deco.name, deco.diary_id
+            )));
+        }
+        let cells = grid
+            .check(&placement.coordinates, deco.footprint())
+            .map_err(|e| AppError::Validation(format!("{}: {}", deco.name, e)))?;
+        placed.push((deco, &placement.coordinates, cells));
+    }
+
+    // every deco caught in an overlap, once, in layout order
+    let conflicts = placed
+        .iter()
+        .enumerate()
+        .filter(|(i, (_, _, cells))| {
+            placed
+                .iter()
+                .enumerate()
+                .any(|(j, (_, _, other))| *i != j && cells.overlaps(other))
+        })
+        .map(|(_, (deco, coordinates, _))| PlacementConflict::of(deco, coordinates))
+        .collect::<Vec<_>>();
+    if !conflicts.is_empty() {
+        return Err(conflict_error(conflicts));
+    }
+
+    Ok(decos
+        .iter()
+        .map(|deco| {
+            let coordinates = placed
+                .iter()
+                .find(|(placed, _, _)| {
+                    (placed.diary_id, placed.deco_id) == (deco.diary_id, deco.deco_id)
+                })
+                .map(|(_, coordinates, _)| (*coordinates).clone());
+            (deco, coordinates)
+        })
+        .collect())
+}
+
+/// Checks a full layout and moves the room's decos to it.
+pub async fn apply_layout(
+    tx: &mut PgConnection,
+    grid: &RoomGrid,
+    user_id: Uuid,
+    decos: &[PlacedDeco],
+    placements: &[LayoutPlacement],
+) -> AppResult<()> {
+    for (deco, coordinates) in check_layout(grid, decos, placements)? {
+        update_user_deco(tx, user_id, deco.diary_id, deco.deco_id, coordinates).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use serde::Serialize;
+use serde_json::json;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    db::user_deco::{get_room_decos, PlacedDeco},
+    error::{AppError, AppResult},
+    utils::coordinates::Coordinates,
+};
+
+use super::grid::{Cells, RoomGrid};
+
+/// A placed deco in the way, listed in the 409 body.
+#[derive(Serialize, Clone, Debug)]
+pub struct PlacementConflict {
+    pub diary_id: i64,
+    pub deco_id: i64,
+    pub name: String,
+    pub coordinates: Coordinates,
+}
+
+impl PlacementConflict {
+    pub fn of(deco: &PlacedDeco, coordinates: &Coordinates) -> Self {
+        Self {
+            diary_id: deco.diary_id,
+            deco_id: deco.deco_id,
+            name: deco.name.clone(),
+            coordinates: coordinates.clone(),
+        }
+    }
+}
+
+pub fn conflict_error(conflicts: Vec<PlacementConflict>) -> AppError {
+    AppError::ConflictWith(
+        format!(
+            "The placement overlaps {}",
+            conflicts
+                .iter()
+                .map(|conflict| conflict.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        json!({ "conflicts": conflicts }),
+    )
+}
+
+/// Checks that `target` can go to `coordinates` in the room of its diary's
+/// month: a valid orientation, inside the room and clear of the other placed
+/// decos. The room must already be locked by the transaction.
+pub async fn check_placement(
+    tx: &mut PgConnection,
+    grid: &RoomGrid,
+    user_id: Uuid,
+    target: &PlacedDeco,
+    coordinates: &Coordinates,
+) -> AppResult<()> {
+    let cells = grid
+        .check(coordinates, target.footprint())
+        .map_err(AppError::Validation)?;
+
+    let (year, month) = target.room();
+    let conflicts = get_room_decos(tx, user_id, year, month)
+        .await?
+        .into_iter()
+        .filter(|other| (other.diary_id, other.deco_id) != (target.diary_id, target.deco_id))
+        .filter_map(|other| {
+            let other_coordinates = other.coordinates.as_ref()?.0.clone();
+            Cells::of(&other_coordinates, other.footprint())
+                .overlaps(&cells)
+                .then(|| PlacementConflict::of(&other, &other_coordinates))
+        })
+        .collect::<Vec<_>>();
+    if !conflicts.is_empty() {
+        return Err(conflict_error(conflicts));
+    }
+    Ok(())
+}
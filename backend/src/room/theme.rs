@@ -0,0 +1,147 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        deco::{get_deco, Deco},
+        room::{get_room_theme, RoomTheme},
+    },
+    error::{AppError, AppResult},
+};
+
+use super::grid::RoomGrid;
+
+/// The parts of a room's look, each picked from catalog decos of its own
+/// category. Theme decos are never placed in the room themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThemeSlot {
+    Wallpaper,
+    Floor,
+    Lighting,
+    // the deco's footprint is the size of the room
+    Size,
+}
+
+impl ThemeSlot {
+    pub const ALL: [ThemeSlot; 4] = [
+        ThemeSlot::Wallpaper,
+        ThemeSlot::Floor,
+        ThemeSlot::Lighting,
+        ThemeSlot::Size,
+    ];
+
+    pub fn category(self) -> &'static str {
+        match self {
+            ThemeSlot::Wallpaper => "wallpaper",
+            ThemeSlot::Floor => "floor",
+            ThemeSlot::Lighting => "lighting",
+            ThemeSlot::Size => "room_size",
+        }
+    }
+
+    pub fn of_category(category: Option<&str>) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|slot| Some(slot.category()) == category)
+    }
+
+    pub fn deco_id(self, theme: &RoomTheme) -> Option<i64> {
+        match self {
+            ThemeSlot::Wallpaper => theme.wallpaper_id,
+            ThemeSlot::Floor => theme.floor_id,
+            ThemeSlot::Lighting => theme.lighting_id,
+            ThemeSlot::Size => theme.size_id,
+        }
+    }
+}
+
+/// The decos of a room's theme.
+#[derive(Clone, Default, Debug)]
+pub struct ThemeDecos {
+    pub wallpaper: Option<Deco>,
+    pub floor: Option<Deco>,
+    pub lighting: Option<Deco>,
+    pub size: Option<Deco>,
+}
+
+impl ThemeDecos {
+    pub async fn load(tx: &mut PgConnection, theme: &RoomTheme) -> AppResult<Self> {
+        let mut decos = Self::default();
+        for slot in ThemeSlot::ALL {
+            let Some(deco_id) = slot.deco_id(theme) else {
+                continue;
+            };
+            *decos.slot_mut(slot) = get_deco(tx, deco_id).await?;
+        }
+        Ok(decos)
+    }
+
+    /// Loads the decos of a theme about to be set, which must all exist, be
+    /// valid and belong to the category of their slot.
+    pub async fn load_checked(tx: &mut PgConnection, theme: &RoomTheme) -> AppResult<Self> {
+        let decos = Self::load(tx, theme).await?;
+        for slot in ThemeSlot::ALL {
+            let Some(deco_id) = slot.deco_id(theme) else {
+                continue;
+            };
+            let deco = decos
+                .slot(slot)
+                .filter(|deco| deco.is_valid)
+                .ok_or_else(|| AppError::Validation(format!("Deco {} does not exist", deco_id)))?;
+            if deco.category.as_deref() != Some(slot.category()) {
+                return Err(AppError::Validation(format!(
+                    "{} is not a {} deco",
+                    deco.name,
+                    slot.category()
+                )));
+            }
+        }
+        Ok(decos)
+    }
+
+    fn slot(&self, slot: ThemeSlot) -> Option<&Deco> {
+        match slot {
+            ThemeSlot::Wallpaper => self.wallpaper.as_ref(),
+            ThemeSlot::Floor => self.floor.as_ref(),
+            ThemeSlot::Lighting => self.lighting.as_ref(),
+            ThemeSlot::Size => self.size.as_ref(),
+        }
+    }
+
+    fn slot_mut(&mut self, slot: ThemeSlot) -> &mut Option<Deco> {
+        match slot {
+            ThemeSlot::Wallpaper => &mut self.wallpaper,
+            ThemeSlot::Floor => &mut self.floor,
+            ThemeSlot::Lighting => &mut self.lighting,
+            ThemeSlot::Size => &mut self.size,
+        }
+    }
+
+    /// The grid of the room: the size deco's footprint, `default` without one.
+    pub fn grid(&self, default: RoomGrid) -> RoomGrid {
+        match &self.size {
+            Some(size) => RoomGrid {
+                width: size.width as i64,
+                depth: size.depth as i64,
+                height: size.height as i64,
+            },
+            None => default,
+        }
+    }
+}
+
+/// The grid of a user's room of a month, see `ThemeDecos::grid`.
+pub async fn room_grid(
+    tx: &mut PgConnection,
+    default: RoomGrid,
+    user_id: Uuid,
+    year: i32,
+    month: u32,
+) -> AppResult<RoomGrid> {
+    let theme = get_room_theme(tx, user_id, year, month).await?;
+    let size = RoomTheme {
+        size_id: theme.size_id,
+        ..RoomTheme::default()
+    };
+    Ok(ThemeDecos::load(tx, &size).await?.grid(default))
+}
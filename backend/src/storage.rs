@@ -1,2 +1,7 @@
 pub mod client;
+pub mod deco;
 pub mod diary;
+pub mod links;
+pub mod local;
+pub mod s3;
+pub mod store;
@@ -1,7 +1,22 @@
-use anyhow::anyhow;
+use std::{path::Path, time::Duration};
 
-use reqwest::{Client, Error as ReqwestError};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{
+    header::{CONTENT_TYPE, LOCATION},
+    Client, RequestBuilder, Url,
+};
+use serde::Deserialize;
 use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use super::store::{Bucket, ObjectStore};
+
+// page size of the storage list endpoint
+const LIST_PAGE_SIZE: usize = 1000;
+// supabase requires resumable uploads to be sent in 6MB chunks
+const TUS_CHUNK_SIZE: u64 = 6 * 1024 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct SupabaseClient {
@@ -12,6 +27,13 @@ pub struct SupabaseClient {
     model_bucket: String,
 }
 
+#[derive(Deserialize, Debug)]
+struct ListedObject {
+    name: String,
+    // folders are listed without an id
+    id: Option<String>,
+}
+
 impl SupabaseClient {
     pub fn new(
         supabase_url: String,
@@ -29,108 +51,254 @@ impl SupabaseClient {
         }
     }
 
-    pub async fn download(
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |key: &str| std::env::var(key).map_err(|_| anyhow!("{} must be set", key));
+        Ok(Self::new(
+            var("SUPABASE_URL")?,
+            var("SUPABASE_KEY")?,
+            var("SUPABASE_AUDIO_BUCKET")?,
+            var("SUPABASE_MODEL_BUCKET")?,
+        ))
+    }
+
+    fn bucket_name(&self, bucket: Bucket) -> &str {
+        match bucket {
+            Bucket::Audio => &self.audio_bucket,
+            Bucket::Model => &self.model_bucket,
+        }
+    }
+
+    fn authorized(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+            .header("apikey", &self.api_key)
+            .header("authorization", &format!("Bearer {}", &self.api_key))
+    }
+}
+
+#[async_trait]
+impl ObjectStore for SupabaseClient {
+    async fn put(
         &self,
-        bucket: String,
-        filename: String,
-    ) -> Result<Vec<u8>, ReqwestError> {
+        bucket: Bucket,
+        key: &str,
+        content: Vec<u8>,
+        content_type: &str,
+    ) -> anyhow::Result<()> {
+        let url: String = format!(
+            "{}/storage/v1/object/{}/{}",
+            self.supabase_url,
+            self.bucket_name(bucket),
+            key
+        );
+
+        let resp = self
+            .authorized(self.client.post(&url))
+            .header(CONTENT_TYPE, content_type)
+            .body(content)
+            .send()
+            .await?;
+
+        match resp {
+            r if r.status().is_success() => Ok(()),
+            r => {
+                tracing::error!("Error uploading file: {:?}", r);
+                Err(r.error_for_status().unwrap_err().into())
+            }
+        }
+    }
+
+    async fn get(&self, bucket: Bucket, key: &str) -> anyhow::Result<Vec<u8>> {
         let url: String = format!(
-            "{}/storage/v1/object/public/{}/{}",
-            self.supabase_url, bucket, filename
+            "{}/storage/v1/object/authenticated/{}/{}",
+            self.supabase_url,
+            self.bucket_name(bucket),
+            key
         );
-        let resp = self.client.get(&url).send().await?;
+        let resp = self
+            .authorized(self.client.get(&url))
+            .send()
+            .await?
+            .error_for_status()?;
 
         Ok(resp.bytes().await?.to_vec())
     }
 
-    pub async fn upload_diary(
+    // resumable (TUS) upload, one chunk in memory at a time
+    async fn put_file(
         &self,
-        audio: Vec<u8>,
-        filename: &str,
-    ) -> Result<String, anyhow::Error> {
-        self.upload(self.audio_bucket.clone(), filename, audio)
-            .await?;
-        let presigned_suffix = self
-            .get_presigned_download_url(self.audio_bucket.clone(), filename)
-            .await?;
+        bucket: Bucket,
+        key: &str,
+        path: &Path,
+        content_type: &str,
+    ) -> anyhow::Result<()> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let length = file.metadata().await?.len();
+        let metadata = format!(
+            "bucketName {},objectName {},contentType {}",
+            STANDARD.encode(self.bucket_name(bucket)),
+            STANDARD.encode(key),
+            STANDARD.encode(content_type)
+        );
 
-        Ok(format!(
-            "{}/storage/v1/{}",
-            self.supabase_url, presigned_suffix
-        ))
+        let endpoint = Url::parse(&format!(
+            "{}/storage/v1/upload/resumable",
+            self.supabase_url
+        ))?;
+        let resp = self
+            .authorized(self.client.post(endpoint.clone()))
+            .header("tus-resumable", "1.0.0")
+            .header("upload-length", length)
+            .header("upload-metadata", metadata)
+            .send()
+            .await?
+            .error_for_status()?;
+        let location = resp
+            .headers()
+            .get(LOCATION)
+            .ok_or_else(|| anyhow!("Resumable upload was created without a location"))?
+            .to_str()?;
+        let upload_url = endpoint.join(location)?;
+
+        let mut offset = 0;
+        while offset < length {
+            let mut chunk = Vec::with_capacity(TUS_CHUNK_SIZE as usize);
+            (&mut file)
+                .take(TUS_CHUNK_SIZE)
+                .read_to_end(&mut chunk)
+                .await?;
+            if chunk.is_empty() {
+                return Err(anyhow!("{} shrank while uploading", path.display()));
+            }
+            let chunk_len = chunk.len() as u64;
+            self.authorized(self.client.patch(upload_url.clone()))
+                .header("tus-resumable", "1.0.0")
+                .header("upload-offset", offset)
+                .header(CONTENT_TYPE, "application/offset+octet-stream")
+                .body(chunk)
+                .send()
+                .await?
+                .error_for_status()?;
+            offset += chunk_len;
+        }
+        Ok(())
     }
 
-    pub async fn upload_model(
-        &self,
-        model: Vec<u8>,
-        filename: &str,
-    ) -> Result<String, anyhow::Error> {
-        self.upload(self.model_bucket.clone(), filename, model)
-            .await?;
-        let presigned_suffix = self
-            .get_presigned_download_url(self.model_bucket.clone(), filename)
-            .await?;
+    async fn get_file(&self, bucket: Bucket, key: &str, path: &Path) -> anyhow::Result<()> {
+        let url: String = format!(
+            "{}/storage/v1/object/authenticated/{}/{}",
+            self.supabase_url,
+            self.bucket_name(bucket),
+            key
+        );
+        let mut resp = self
+            .authorized(self.client.get(&url))
+            .send()
+            .await?
+            .error_for_status()?;
 
-        Ok(format!(
-            "{}/storage/v1/{}",
-            self.supabase_url, presigned_suffix
-        ))
+        let mut file = tokio::fs::File::create(path).await?;
+        while let Some(chunk) = resp.chunk().await? {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        Ok(())
     }
 
-    pub async fn upload(
-        &self,
-        bucket: String,
-        filename: &str,
-        file: Vec<u8>,
-    ) -> Result<(), ReqwestError> {
+    async fn delete(&self, bucket: Bucket, key: &str) -> anyhow::Result<()> {
         let url: String = format!(
             "{}/storage/v1/object/{}/{}",
-            self.supabase_url, bucket, filename
+            self.supabase_url,
+            self.bucket_name(bucket),
+            key
         );
 
-        let resp = self
-            .client
-            .post(&url)
-            .header("apikey", &self.api_key)
-            .header("authorization", &format!("Bearer {}", &self.api_key))
-            .body(file)
-            .send()
-            .await?;
+        let resp = self.authorized(self.client.delete(&url)).send().await?;
 
         match resp {
-            r if r.status().is_success() => Ok(()),
+            // already gone
+            r if r.status().is_success() || r.status() == reqwest::StatusCode::NOT_FOUND => Ok(()),
             r => {
-                tracing::error!("Error uploading file: {:?}", r);
-                Err(r.error_for_status().unwrap_err())
+                tracing::error!("Error deleting file: {:?}", r);
+                Err(r.error_for_status().unwrap_err().into())
             }
         }
     }
-    pub async fn get_presigned_download_url(
+
+    async fn sign(
         &self,
-        bucket: String,
-        filename: &str,
-    ) -> Result<String, anyhow::Error> {
+        bucket: Bucket,
+        key: &str,
+        expires_in: Duration,
+    ) -> anyhow::Result<String> {
         let url = format!(
             "{}/storage/v1/object/sign/{}/{}",
-            self.supabase_url, bucket, filename
+            self.supabase_url,
+            self.bucket_name(bucket),
+            key
         );
 
         let resp = self
-            .client
-            .post(&url)
-            .header("apikey", &self.api_key)
-            .header("authorization", &format!("Bearer {}", &self.api_key))
+            .authorized(self.client.post(&url))
             .json(&json!({
-                "expiresIn": 7776000, // 90 days
-            })) // 90 days
+                "expiresIn": expires_in.as_secs(),
+            }))
             .send()
             .await?;
 
         let json = resp.json::<serde_json::Value>().await?;
         let Some(signed_url) = json["signedURL"].as_str() else {
-            let error_message = json["message"].as_str().unwrap().to_string();
+            let error_message = json["message"]
+                .as_str()
+                .unwrap_or("no signedURL")
+                .to_string();
             return Err(anyhow!(error_message));
         };
-        Ok(signed_url.to_string())
+        Ok(format!("{}/storage/v1/{}", self.supabase_url, signed_url))
+    }
+
+    async fn list(&self, bucket: Bucket, prefix: &str) -> anyhow::Result<Vec<String>> {
+        let url = format!(
+            "{}/storage/v1/object/list/{}",
+            self.supabase_url,
+            self.bucket_name(bucket)
+        );
+        // supabase lists one folder at a time, searching within it by name
+        let (folder, search) = match prefix.rsplit_once('/') {
+            Some((folder, search)) => (folder, search),
+            None => ("", prefix),
+        };
+
+        let mut keys = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = self
+                .authorized(self.client.post(&url))
+                .json(&json!({
+                    "prefix": folder,
+                    "search": search,
+                    "limit": LIST_PAGE_SIZE,
+                    "offset": offset,
+                }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Vec<ListedObject>>()
+                .await?;
+            let page_len = page.len();
+            keys.extend(
+                page.into_iter()
+                    .filter(|object| object.id.is_some())
+                    .map(|object| match folder {
+                        "" => object.name,
+                        folder => format!("{}/{}", folder, object.name),
+                    })
+                    .filter(|key| key.starts_with(prefix)),
+            );
+            if page_len < LIST_PAGE_SIZE {
+                break;
+            }
+            offset += page_len;
+        }
+        Ok(keys)
     }
 }
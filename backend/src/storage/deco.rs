@@ -0,0 +1,16 @@
+use std::path::Path;
+
+use super::store::{Bucket, ObjectStore};
+
+// models are opaque to the server, clients know their format by name
+const MODEL_CONTENT_TYPE: &str = "application/octet-stream";
+
+pub async fn upload_deco_model(
+    object_store: &dyn ObjectStore,
+    name: &str,
+    model: &Path,
+) -> anyhow::Result<()> {
+    object_store
+        .put_file(Bucket::Model, name, model, MODEL_CONTENT_TYPE)
+        .await
+}
@@ -1,10 +1,31 @@
-use super::client::SupabaseClient;
-
-pub async fn get_diary_audio(
-    supabase_client: SupabaseClient,
-    audio_link: String,
-) -> Result<Vec<u8>, reqwest::Error> {
-    supabase_client
-        .download("diary".to_string(), audio_link)
+use std::path::Path;
+
+use super::store::{Bucket, ObjectStore};
+
+pub async fn upload_diary_audio(
+    object_store: &dyn ObjectStore,
+    audio_title: &str,
+    audio: &Path,
+    content_type: &str,
+) -> anyhow::Result<()> {
+    object_store
+        .put_file(Bucket::Audio, audio_title, audio, content_type)
         .await
 }
+
+pub async fn download_diary_audio(
+    object_store: &dyn ObjectStore,
+    audio_title: &str,
+    dest: &Path,
+) -> anyhow::Result<()> {
+    object_store
+        .get_file(Bucket::Audio, audio_title, dest)
+        .await
+}
+
+pub async fn delete_diary_audio(
+    object_store: &dyn ObjectStore,
+    audio_title: &str,
+) -> anyhow::Result<()> {
+    object_store.delete(Bucket::Audio, audio_title).await
+}
@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::future::join_all;
+use serde::Serialize;
+
+use crate::db::{deco::Deco, diary::Diary, user_deco::UserDeco};
+
+use super::store::{Bucket, ObjectStore};
+
+// upper bound on cached links, expired ones are evicted first
+const CACHE_CAPACITY: usize = 10_000;
+
+/// Mints short-lived download links for stored objects at read time. Links
+/// are cached and handed out again while at least half their lifetime is
+/// left, so a client polling the same page gets stable URLs.
+pub struct LinkSigner {
+    object_store: Arc<dyn ObjectStore>,
+    ttl: Duration,
+    cache: Mutex<HashMap<(Bucket, String), (String, Instant)>>,
+}
+
+impl LinkSigner {
+    pub fn new(object_store: Arc<dyn ObjectStore>, ttl: Duration) -> Self {
+        Self {
+            object_store,
+            ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_env(object_store: Arc<dyn ObjectStore>) -> Self {
+        let ttl = std::env::var("SIGNED_URL_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(900);
+        Self::new(object_store, Duration::from_secs(ttl))
+    }
+
+    /// Returns `None` (and logs) if the link could not be minted, so one
+    /// broken object does not fail a whole listing.
+    pub async fn sign(&self, bucket: Bucket, key: &str) -> Option<String> {
+        let cache_key = (bucket, key.to_string());
+        let now = Instant::now();
+        if let Some((url, expires_at)) = self.cache.lock().unwrap().get(&cache_key) {
+            if *expires_at > now + self.ttl / 2 {
+                return Some(url.clone());
+            }
+        }
+
+        let url = match self.object_store.sign(bucket, key, self.ttl).await {
+            Ok(url) => url,
+            Err(e) => {
+                tracing::error!("Failed to sign {}/{}: {:#}", bucket.as_str(), key, e);
+                return None;
+            }
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= CACHE_CAPACITY {
+            cache.retain(|_, (_, expires_at)| *expires_at > now);
+            if cache.len() >= CACHE_CAPACITY {
+                cache.clear();
+            }
+        }
+        cache.insert(cache_key, (url.clone(), now + self.ttl));
+        Some(url)
+    }
+
+    async fn sign_opt(&self, bucket: Bucket, key: Option<&str>) -> Option<String> {
+        match key {
+            Some(key) => self.sign(bucket, key).await,
+            None => None,
+        }
+    }
+
+    pub async fn diary(&self, diary: Diary) -> SignedDiary {
+        let audio_link = self
+            .sign_opt(Bucket::Audio, diary.audio_key.as_deref())
+            .await;
+        SignedDiary { diary, audio_link }
+    }
+
+    pub async fn diaries(&self, diaries: Vec<Diary>) -> Vec<SignedDiary> {
+        join_all(diaries.into_iter().map(|diary| self.diary(diary))).await
+    }
+
+    pub async fn deco(&self, deco: Deco) -> SignedDeco {
+        let asset_link = self
+            .sign_opt(Bucket::Model, deco.asset_key.as_deref())
+            .await;
+        SignedDeco { deco, asset_link }
+    }
+
+    pub async fn decos(&self, decos: Vec<Deco>) -> Vec<SignedDeco> {
+        join_all(decos.into_iter().map(|deco| self.deco(deco))).await
+    }
+
+    pub async fn user_decos(&self, user_decos: Vec<UserDeco>) -> Vec<SignedUserDeco> {
+        join_all(user_decos.into_iter().map(|user_deco| async move {
+            let asset_link = self
+                .sign_opt(Bucket::Model, user_deco.asset_key.as_deref())
+                .await;
+            let audio_link = self
+                .sign_opt(Bucket::Audio, user_deco.audio_key.as_deref())
+                .await;
+            SignedUserDeco {
+                user_deco,
+                asset_link,
+                audio_link,
+            }
+        }))
+        .await
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SignedDiary {
+    #[serde(flatten)]
+    pub diary: Diary,
+    pub audio_link: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SignedDeco {
+    #[serde(flatten)]
+    pub deco: Deco,
+    pub asset_link: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SignedUserDeco {
+    #[serde(flatten)]
+    pub user_deco: UserDeco,
+    pub asset_link: Option<String>,
+    pub audio_link: Option<String>,
+}
@@ -0,0 +1,206 @@
+use std::{
+    path::{Component, Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::Url;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use super::store::{Bucket, ObjectStore};
+
+/// Stores objects under a directory on disk, one subdirectory per bucket.
+/// Signed URLs point back at this server (`GET /storage/:bucket/*key`) and
+/// carry an HMAC of the object and its expiry.
+#[derive(Clone)]
+pub struct LocalObjectStore {
+    root: PathBuf,
+    public_url: Url,
+    signing_key: Vec<u8>,
+}
+
+impl LocalObjectStore {
+    pub fn new(root: PathBuf, public_url: Url, signing_key: Vec<u8>) -> Self {
+        Self {
+            root,
+            public_url,
+            signing_key,
+        }
+    }
+
+    pub fn from_env() -> anyhow::Result<Self> {
+        let root = std::env::var("STORAGE_LOCAL_ROOT").unwrap_or_else(|_| "./storage".to_string());
+        let public_url = match std::env::var("STORAGE_PUBLIC_URL") {
+            Ok(url) => url,
+            Err(_) => format!(
+                "http://localhost:{}",
+                std::env::var("PORT").unwrap_or_else(|_| "3000".to_string())
+            ),
+        };
+        let signing_key = match std::env::var("STORAGE_SIGNING_KEY") {
+            Ok(key) => key.into_bytes(),
+            Err(_) => {
+                tracing::warn!(
+                    "STORAGE_SIGNING_KEY is not set, signed URLs will not survive a restart"
+                );
+                [Uuid::new_v4().into_bytes(), Uuid::new_v4().into_bytes()].concat()
+            }
+        };
+        Ok(Self::new(
+            PathBuf::from(root),
+            Url::parse(&public_url)?,
+            signing_key,
+        ))
+    }
+
+    /// Where the object lives on disk; rejects keys escaping the bucket.
+    pub fn path(&self, bucket: Bucket, key: &str) -> anyhow::Result<PathBuf> {
+        let relative = Path::new(key);
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(anyhow!("Invalid object key: {}", key));
+        }
+        Ok(self.root.join(bucket.as_str()).join(relative))
+    }
+
+    fn mac(&self, bucket: Bucket, key: &str, expires: u64) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.signing_key)
+            .expect("HMAC accepts keys of any length");
+        mac.update(format!("{}/{}\n{}", bucket.as_str(), key, expires).as_bytes());
+        mac
+    }
+
+    /// Checks a signature minted by `sign` and that it has not expired yet.
+    pub fn verify(&self, bucket: Bucket, key: &str, expires: u64, signature: &str) -> bool {
+        if expires < unix_now() {
+            return false;
+        }
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        self.mac(bucket, key, expires)
+            .verify_slice(&signature)
+            .is_ok()
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[async_trait]
+impl ObjectStore for LocalObjectStore {
+    // signed URLs serve every object as a download, so the content type is not kept
+    async fn put(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        content: Vec<u8>,
+        _content_type: &str,
+    ) -> anyhow::Result<()> {
+        let path = self.path(bucket, key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, content).await?;
+        Ok(())
+    }
+
+    async fn get(&self, bucket: Bucket, key: &str) -> anyhow::Result<Vec<u8>> {
+        Ok(tokio::fs::read(self.path(bucket, key)?).await?)
+    }
+
+    async fn put_file(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        path: &Path,
+        _content_type: &str,
+    ) -> anyhow::Result<()> {
+        let dest = self.path(bucket, key)?;
+        if let Some(parent) = dest.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(path, dest).await?;
+        Ok(())
+    }
+
+    async fn get_file(&self, bucket: Bucket, key: &str, path: &Path) -> anyhow::Result<()> {
+        tokio::fs::copy(self.path(bucket, key)?, path).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, bucket: Bucket, key: &str) -> anyhow::Result<()> {
+        match tokio::fs::remove_file(self.path(bucket, key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn sign(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expires_in: Duration,
+    ) -> anyhow::Result<String> {
+        self.path(bucket, key)?;
+        let expires = unix_now() + expires_in.as_secs();
+        let signature = hex::encode(self.mac(bucket, key, expires).finalize().into_bytes());
+
+        let mut url = self.public_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("STORAGE_PUBLIC_URL cannot be a base URL"))?
+            .pop_if_empty()
+            .extend(["storage", bucket.as_str()])
+            .extend(key.split('/'));
+        url.query_pairs_mut()
+            .append_pair("expires", &expires.to_string())
+            .append_pair("signature", &signature);
+        Ok(url.to_string())
+    }
+
+    async fn list(&self, bucket: Bucket, prefix: &str) -> anyhow::Result<Vec<String>> {
+        let bucket_root = self.root.join(bucket.as_str());
+        let mut keys = Vec::new();
+        let mut dirs = vec![bucket_root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                // nothing has been stored in the bucket yet
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let key = path
+                    .strip_prefix(&bucket_root)?
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                if key.starts_with(prefix) {
+                    keys.push(key);
+                }
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn as_local(&self) -> Option<&LocalObjectStore> {
+        Some(self)
+    }
+}
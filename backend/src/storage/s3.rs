@@ -0,0 +1,179 @@
+use std::{path::Path as FsPath, time::Duration};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use object_store::{
+    aws::{AmazonS3, AmazonS3Builder},
+    path::Path,
+    signer::Signer,
+    Attribute, Attributes, ObjectStore as _, PutMultipartOpts, PutOptions, WriteMultipart,
+};
+use reqwest::Method;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use super::store::{Bucket, ObjectStore};
+
+// parts uploaded concurrently by `put_file`, each buffering up to 5MB
+const MAX_CONCURRENT_PARTS: usize = 4;
+
+/// Any S3-compatible service (AWS, MinIO, R2, ...). Requests are path-style,
+/// which MinIO requires, unless `S3_VIRTUAL_HOSTED` is set for services that
+/// only take virtual-hosted-style ones.
+#[derive(Debug)]
+pub struct S3ObjectStore {
+    audio: AmazonS3,
+    model: AmazonS3,
+}
+
+impl S3ObjectStore {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |key: &str| std::env::var(key).map_err(|_| anyhow!("{} must be set", key));
+        let flag = |key: &str| {
+            std::env::var(key)
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false)
+        };
+
+        let mut builder = AmazonS3Builder::new()
+            .with_region(std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()))
+            .with_access_key_id(var("S3_ACCESS_KEY_ID")?)
+            .with_secret_access_key(var("S3_SECRET_ACCESS_KEY")?)
+            .with_allow_http(flag("S3_ALLOW_HTTP"))
+            .with_virtual_hosted_style_request(flag("S3_VIRTUAL_HOSTED"));
+        if let Ok(endpoint) = std::env::var("S3_ENDPOINT") {
+            builder = builder.with_endpoint(endpoint);
+        }
+
+        Ok(Self {
+            audio: builder
+                .clone()
+                .with_bucket_name(var("S3_AUDIO_BUCKET")?)
+                .build()?,
+            model: builder.with_bucket_name(var("S3_MODEL_BUCKET")?).build()?,
+        })
+    }
+
+    fn bucket(&self, bucket: Bucket) -> &AmazonS3 {
+        match bucket {
+            Bucket::Audio => &self.audio,
+            Bucket::Model => &self.model,
+        }
+    }
+}
+
+fn content_type_attribute(content_type: &str) -> Attributes {
+    Attributes::from_iter([(Attribute::ContentType, content_type.to_string())])
+}
+
+#[async_trait]
+impl ObjectStore for S3ObjectStore {
+    async fn put(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        content: Vec<u8>,
+        content_type: &str,
+    ) -> anyhow::Result<()> {
+        let options = PutOptions {
+            attributes: content_type_attribute(content_type),
+            ..PutOptions::default()
+        };
+        self.bucket(bucket)
+            .put_opts(&Path::from(key), content.into(), options)
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, bucket: Bucket, key: &str) -> anyhow::Result<Vec<u8>> {
+        let object = self.bucket(bucket).get(&Path::from(key)).await?;
+        Ok(object.bytes().await?.to_vec())
+    }
+
+    async fn put_file(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        path: &FsPath,
+        content_type: &str,
+    ) -> anyhow::Result<()> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let options = PutMultipartOpts {
+            attributes: content_type_attribute(content_type),
+            ..PutMultipartOpts::default()
+        };
+        let mut upload = WriteMultipart::new(
+            self.bucket(bucket)
+                .put_multipart_opts(&Path::from(key), options)
+                .await?,
+        );
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let read = match file.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) => {
+                    upload.abort().await?;
+                    return Err(e.into());
+                }
+            };
+            upload.write(&buf[..read]);
+            if let Err(e) = upload.wait_for_capacity(MAX_CONCURRENT_PARTS).await {
+                upload.abort().await?;
+                return Err(e.into());
+            }
+        }
+        upload.finish().await?;
+        Ok(())
+    }
+
+    async fn get_file(&self, bucket: Bucket, key: &str, path: &FsPath) -> anyhow::Result<()> {
+        let mut stream = self
+            .bucket(bucket)
+            .get(&Path::from(key))
+            .await?
+            .into_stream();
+        let mut file = tokio::fs::File::create(path).await?;
+        while let Some(chunk) = stream.try_next().await? {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        Ok(())
+    }
+
+    async fn delete(&self, bucket: Bucket, key: &str) -> anyhow::Result<()> {
+        match self.bucket(bucket).delete(&Path::from(key)).await {
+            // already gone
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn sign(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        expires_in: Duration,
+    ) -> anyhow::Result<String> {
+        let url = self
+            .bucket(bucket)
+            .signed_url(Method::GET, &Path::from(key), expires_in)
+            .await?;
+        Ok(url.to_string())
+    }
+
+    async fn list(&self, bucket: Bucket, prefix: &str) -> anyhow::Result<Vec<String>> {
+        // object_store lists whole path segments, the rest is matched here
+        let folder = prefix
+            .rsplit_once('/')
+            .map(|(folder, _)| Path::from(folder));
+        let keys = self
+            .bucket(bucket)
+            .list(folder.as_ref())
+            .map_ok(|meta| meta.location.to_string())
+            .try_filter(|key| futures::future::ready(key.starts_with(prefix)))
+            .try_collect()
+            .await?;
+        Ok(keys)
+    }
+}
@@ -0,0 +1,86 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{client::SupabaseClient, local::LocalObjectStore, s3::S3ObjectStore};
+
+/// Logical bucket an object belongs to. Each backend maps it to its own
+/// bucket (or directory) name.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Bucket {
+    Audio,
+    Model,
+}
+
+impl Bucket {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Bucket::Audio => "audio",
+            Bucket::Model => "model",
+        }
+    }
+
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "audio" => Some(Bucket::Audio),
+            "model" => Some(Bucket::Model),
+            _ => None,
+        }
+    }
+}
+
+/// Blob storage for diary recordings and deco models.
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// `content_type` is served back with the object, where the backend keeps it.
+    async fn put(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        content: Vec<u8>,
+        content_type: &str,
+    ) -> anyhow::Result<()>;
+
+    async fn get(&self, bucket: Bucket, key: &str) -> anyhow::Result<Vec<u8>>;
+
+    /// Like `put`, but streams the file from disk instead of holding it in memory.
+    async fn put_file(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        path: &Path,
+        content_type: &str,
+    ) -> anyhow::Result<()>;
+
+    /// Like `get`, but streams the object into a file on disk.
+    async fn get_file(&self, bucket: Bucket, key: &str, path: &Path) -> anyhow::Result<()>;
+
+    /// Deleting a missing object is not an error.
+    async fn delete(&self, bucket: Bucket, key: &str) -> anyhow::Result<()>;
+
+    /// Returns a URL anyone can download the object from until it expires.
+    async fn sign(&self, bucket: Bucket, key: &str, expires_in: Duration)
+        -> anyhow::Result<String>;
+
+    /// Keys in the bucket starting with `prefix`.
+    async fn list(&self, bucket: Bucket, prefix: &str) -> anyhow::Result<Vec<String>>;
+
+    /// The local backend serves its own signed URLs, see `handlers::storage`.
+    fn as_local(&self) -> Option<&LocalObjectStore> {
+        None
+    }
+}
+
+/// Picks the backend named by `STORAGE_BACKEND` (`supabase`, `local` or `s3`).
+pub fn object_store_from_env() -> anyhow::Result<Arc<dyn ObjectStore>> {
+    let backend = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| "supabase".to_string());
+    match backend.as_str() {
+        "supabase" => Ok(Arc::new(SupabaseClient::from_env()?)),
+        "local" => Ok(Arc::new(LocalObjectStore::from_env()?)),
+        "s3" => Ok(Arc::new(S3ObjectStore::from_env()?)),
+        other => Err(anyhow!("Unknown STORAGE_BACKEND: {}", other)),
+    }
+}
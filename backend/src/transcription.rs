@@ -0,0 +1,5 @@
+pub mod chunked;
+pub mod fake;
+pub mod transcriber;
+pub mod transcript;
+pub mod whisper_cpp;
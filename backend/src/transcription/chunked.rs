@@ -0,0 +1,404 @@
+use std::{path::Path, sync::Arc};
+
+use anyhow::{anyhow, Context};
+use futures::{stream, StreamExt, TryStreamExt};
+use tokio::process::Command;
+
+use crate::audio::probe::probe_audio;
+
+use super::{
+    transcriber::Transcriber,
+    transcript::{Transcript, TranscriptSegment},
+};
+
+// the transcription API rejects request bodies above 25MB
+const MAX_REQUEST_BYTES: u64 = 24 * 1024 * 1024;
+// roughly the last sentence, well under whisper's 224 token prompt limit
+const PROMPT_TAIL_CHARS: usize = 200;
+// shorter chunks lose too much context at the cuts
+const MIN_CHUNK_SECS: f64 = 10.0;
+
+#[derive(Clone, Debug)]
+pub struct ChunkConfig {
+    pub max_chunk_ms: i32,
+    pub concurrency: usize,
+    pub silence_db: f64,
+    pub min_silence_secs: f64,
+    pub ffmpeg: String,
+    pub ffprobe: String,
+}
+
+impl ChunkConfig {
+    pub fn from_env() -> Self {
+        let env_or = |key: &str, default: f64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            max_chunk_ms: max_chunk_ms(std::env::var("TRANSCRIBE_CHUNK_SECS").ok().as_deref()),
+            concurrency: env_or("TRANSCRIBE_CONCURRENCY", 3.0).max(1.0) as usize,
+            silence_db: env_or("TRANSCRIBE_SILENCE_DB", -35.0),
+            min_silence_secs: env_or("TRANSCRIBE_SILENCE_SECS", 0.5),
+            ffmpeg: std::env::var("FFMPEG_BIN").unwrap_or_else(|_| "ffmpeg".to_string()),
+            ffprobe: std::env::var("FFPROBE_BIN").unwrap_or_else(|_| "ffprobe".to_string()),
+        }
+    }
+}
+
+// `TRANSCRIBE_CHUNK_SECS` in ms, 600s when unset or unreadable
+fn max_chunk_ms(chunk_secs: Option<&str>) -> i32 {
+    let chunk_secs = chunk_secs
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(600.0);
+    (chunk_secs.max(MIN_CHUNK_SECS) * 1000.0) as i32
+}
+
+/// Transcribes recordings of any length. Long or large recordings are cut at
+/// pauses into chunks that are transcribed one after another, each prompted
+/// with the end of the one before, and stitched back together; short ones go
+/// to the backend in one piece.
+pub struct ChunkedTranscriber {
+    transcriber: Arc<dyn Transcriber>,
+    config: ChunkConfig,
+}
+
+impl ChunkedTranscriber {
+    pub fn new(transcriber: Arc<dyn Transcriber>, config: ChunkConfig) -> Self {
+        Self {
+            transcriber,
+            config,
+        }
+    }
+
+    /// `duration_ms` is the probed length of the recording, if known.
+    pub async fn transcribe(
+        &self,
+        audio: &Path,
+        duration_ms: Option<i32>,
+    ) -> anyhow::Result<Transcript> {
+        let size = tokio::fs::metadata(audio).await?.len();
+        let fits =
+            |duration_ms: i32| duration_ms <= self.config.max_chunk_ms && size <= MAX_REQUEST_BYTES;
+        let duration_ms = match duration_ms {
+            Some(duration_ms) => duration_ms,
+            // probing needs ffprobe, which small recordings can do without
+            None if size <= MAX_REQUEST_BYTES => {
+                return self.transcriber.transcribe(audio, None).await;
+            }
+            None => {
+                probe_audio(&self.config.ffprobe, audio)
+                    .await?
+                    .ok_or_else(|| anyhow!("cannot read the length of {}", audio.display()))?
+                    .duration_ms
+            }
+        };
+
+        if fits(duration_ms) {
+            let transcript = self.transcriber.transcribe(audio, None).await?;
+            return Ok(Transcript::stitch(timed_segments(
+                transcript,
+                0,
+                duration_ms,
+            )));
+        }
+
+        let silences = self.detect_silences(audio).await?;
+        let chunks = plan_chunks(duration_ms, &silences, self.config.max_chunk_ms);
+        tracing::debug!(
+            "Transcribing {} in {} chunks",
+            audio.display(),
+            chunks.len()
+        );
+        let tmp_dir = tempfile::tempdir()?;
+        // cutting is local, so the chunks are cut concurrently
+        let cut = stream::iter(chunks.into_iter().enumerate())
+            .map(|(index, (start_ms, end_ms))| {
+                let chunk_path = tmp_dir.path().join(format!("chunk_{}.m4a", index));
+                async move {
+                    self.extract(audio, start_ms, end_ms, &chunk_path).await?;
+                    anyhow::Ok((chunk_path, start_ms, end_ms))
+                }
+            })
+            .buffered(self.config.concurrency)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let mut segments = Vec::new();
+        let mut prompt = None;
+        for (index, (chunk_path, start_ms, end_ms)) in cut.into_iter().enumerate() {
+            let transcript = self
+                .transcriber
+                .transcribe(&chunk_path, prompt.as_deref())
+                .await
+                .with_context(|| format!("chunk {} failed", index))?;
+            prompt = Some(tail(&transcript.text, PROMPT_TAIL_CHARS));
+            segments.extend(timed_segments(transcript, start_ms, end_ms));
+        }
+        Ok(Transcript::stitch(segments))
+    }
+
+    /// Pauses in the recording as `(start_ms, end_ms)`, using ffmpeg's silencedetect.
+    async fn detect_silences(&self, audio: &Path) -> anyhow::Result<Vec<(i32, i32)>> {
+        let filter = format!(
+            "silencedetect=noise={}dB:d={}",
+            self.config.silence_db, self.config.min_silence_secs
+        );
+        let output = Command::new(&self.config.ffmpeg)
+            .args(["-nostdin", "-hide_banner", "-i"])
+            .arg(audio)
+            .args(["-af", &filter, "-f", "null", "-"])
+            .output()
+            .await
+            .with_context(|| format!("failed to run {}", self.config.ffmpeg))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "ffmpeg silencedetect failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(parse_silences(&String::from_utf8_lossy(&output.stderr)))
+    }
+
+    /// Cuts `[start_ms, end_ms)` into a small mono file, well under the request limit.
+    async fn extract(
+        &self,
+        audio: &Path,
+        start_ms: i32,
+        end_ms: i32,
+        dest: &Path,
+    ) -> anyhow::Result<()> {
+        let output = Command::new(&self.config.ffmpeg)
+            .args(["-nostdin", "-loglevel", "error", "-y", "-ss"])
+            .arg(format!("{:.3}", start_ms as f64 / 1000.0))
+            .arg("-t")
+            .arg(format!("{:.3}", (end_ms - start_ms) as f64 / 1000.0))
+            .arg("-i")
+            .arg(audio)
+            .args([
+                "-vn", "-ac", "1", "-ar", "16000", "-c:a", "aac", "-b:a", "32k",
+            ])
+            .arg(dest)
+            .output()
+            .await
+            .with_context(|| format!("failed to run {}", self.config.ffmpeg))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "ffmpeg failed to cut {}..{}ms: {}",
+                start_ms,
+                end_ms,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn parse_silences(log: &str) -> Vec<(i32, i32)> {
+    let value = |line: &str, key: &str| -> Option<i32> {
+        let rest = &line[line.find(key)? + key.len()..];
+        let number = rest.split_whitespace().next()?;
+        Some((number.parse::<f64>().ok()? * 1000.0) as i32)
+    };
+    let mut silences = Vec::new();
+    let mut start = None;
+    for line in log.lines() {
+        if let Some(s) = value(line, "silence_start:") {
+            start = Some(s.max(0));
+        } else if let Some(end) = value(line, "silence_end:") {
+            if let Some(start) = start.take() {
+                silences.push((start, end));
+            }
+        }
+    }
+    silences
+}
+
+/// Splits `[0, duration_ms)` into chunks of at most `max_chunk_ms`, cutting in
+/// the middle of the last pause of each window when there is one in its second
+/// half, and hard at the window's end otherwise.
+fn plan_chunks(duration_ms: i32, silences: &[(i32, i32)], max_chunk_ms: i32) -> Vec<(i32, i32)> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while duration_ms - start > max_chunk_ms {
+        let window_end = start + max_chunk_ms;
+        let cut = silences
+            .iter()
+            .map(|(silence_start, silence_end)| (silence_start + silence_end) / 2)
+            .rfind(|mid| *mid > start + max_chunk_ms / 2 && *mid <= window_end)
+            .unwrap_or(window_end);
+        chunks.push((start, cut));
+        start = cut;
+    }
+    chunks.push((start, duration_ms));
+    chunks
+}
+
+/// Moves the segments of a transcribed `[start_ms, end_ms)` cut onto the
+/// recording's timeline, or spans the cut with one segment when the backend
+/// gave no timings.
+fn timed_segments(transcript: Transcript, start_ms: i32, end_ms: i32) -> Vec<TranscriptSegment> {
+    if transcript.segments.is_empty() {
+        return vec![TranscriptSegment {
+            start_ms,
+            end_ms,
+            text: transcript.text,
+            speaker: None,
+        }];
+    }
+    transcript
+        .segments
+        .into_iter()
+        .map(|segment| TranscriptSegment {
+            start_ms: (start_ms + segment.start_ms).min(end_ms),
+            end_ms: (start_ms + segment.end_ms).min(end_ms),
+            ..segment
+        })
+        .collect()
+}
+
+fn tail(text: &str, chars: usize) -> String {
+    let skip = text.chars().count().saturating_sub(chars);
+    text.chars().skip(skip).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::fs::PermissionsExt, sync::Mutex};
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    // names each chunk after its file and remembers the prompts it was given
+    #[derive(Default)]
+    struct PromptRecorder {
+        prompts: Mutex<Vec<Option<String>>>,
+    }
+
+    #[async_trait]
+    impl Transcriber for PromptRecorder {
+        async fn transcribe(
+            &self,
+            audio: &Path,
+            prompt: Option<&str>,
+        ) -> anyhow::Result<Transcript> {
+            self.prompts
+                .lock()
+                .unwrap()
+                .push(prompt.map(str::to_string));
+            let name = audio.file_stem().unwrap().to_string_lossy();
+            // the first chunk is the slowest, so it would finish last if the
+            // chunks were transcribed side by side
+            if name == "chunk_0" {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+            Ok(Transcript {
+                text: name.to_string(),
+                segments: vec![],
+            })
+        }
+    }
+
+    #[test]
+    fn short_recordings_are_one_chunk() {
+        assert_eq!(plan_chunks(5_000, &[], 10_000), vec![(0, 5_000)]);
+        assert_eq!(plan_chunks(10_000, &[], 10_000), vec![(0, 10_000)]);
+    }
+
+    #[test]
+    fn cuts_hard_without_pauses() {
+        assert_eq!(
+            plan_chunks(25_000, &[], 10_000),
+            vec![(0, 10_000), (10_000, 20_000), (20_000, 25_000)]
+        );
+    }
+
+    #[test]
+    fn cuts_in_the_last_pause_of_the_second_half() {
+        let silences = [(2_000, 3_000), (7_000, 8_000), (8_500, 9_500)];
+        assert_eq!(
+            plan_chunks(15_000, &silences, 10_000),
+            vec![(0, 9_000), (9_000, 15_000)]
+        );
+        // a pause in the first half would make the chunk too short
+        assert_eq!(
+            plan_chunks(15_000, &[(2_000, 3_000)], 10_000),
+            vec![(0, 10_000), (10_000, 15_000)]
+        );
+    }
+
+    #[test]
+    fn chunks_cover_the_recording() {
+        let silences = [(4_000, 4_400), (13_000, 13_200), (29_000, 31_000)];
+        let chunks = plan_chunks(47_000, &silences, 10_000);
+        assert_eq!(chunks.first().map(|chunk| chunk.0), Some(0));
+        assert_eq!(chunks.last().map(|chunk| chunk.1), Some(47_000));
+        for pair in chunks.windows(2) {
+            assert_eq!(pair[0].1, pair[1].0);
+        }
+        assert!(chunks
+            .iter()
+            .all(|(start, end)| end > start && end - start <= 10_000));
+    }
+
+    #[test]
+    fn chunk_length_is_kept_from_being_empty() {
+        assert_eq!(max_chunk_ms(Some("0")), 10_000);
+        assert_eq!(max_chunk_ms(Some("-5")), 10_000);
+        assert_eq!(max_chunk_ms(Some("90")), 90_000);
+        assert_eq!(max_chunk_ms(Some("ten")), 600_000);
+        assert_eq!(max_chunk_ms(None), 600_000);
+    }
+
+    #[tokio::test]
+    async fn each_chunk_is_prompted_with_the_one_before() {
+        let dir = tempfile::tempdir().unwrap();
+        // finds no pauses and writes an empty file for every cut
+        let ffmpeg = dir.path().join("ffmpeg");
+        std::fs::write(
+            &ffmpeg,
+            "#!/bin/sh\ncase \"$*\" in *silencedetect*) exit 0;; esac\nfor last; do :; done\n: > \"$last\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let audio = dir.path().join("diary.m4a");
+        std::fs::write(&audio, b"audio").unwrap();
+
+        let recorder = Arc::new(PromptRecorder::default());
+        let transcriber = ChunkedTranscriber::new(
+            recorder.clone(),
+            ChunkConfig {
+                max_chunk_ms: 10_000,
+                concurrency: 3,
+                silence_db: -35.0,
+                min_silence_secs: 0.5,
+                ffmpeg: ffmpeg.to_string_lossy().into_owned(),
+                ffprobe: "ffprobe".to_string(),
+            },
+        );
+        let transcript = transcriber.transcribe(&audio, Some(25_000)).await.unwrap();
+
+        assert_eq!(
+            *recorder.prompts.lock().unwrap(),
+            vec![
+                None,
+                Some("chunk_0".to_string()),
+                Some("chunk_1".to_string())
+            ]
+        );
+        assert_eq!(
+            transcript
+                .segments
+                .iter()
+                .map(|segment| (segment.start_ms, segment.end_ms, segment.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, 10_000, "chunk_0"),
+                (10_000, 20_000, "chunk_1"),
+                (20_000, 25_000, "chunk_2")
+            ]
+        );
+    }
+}
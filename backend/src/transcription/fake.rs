@@ -0,0 +1,31 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+
+use super::{transcriber::Transcriber, transcript::Transcript};
+
+/// Deterministic transcriber for tests and offline runs.
+#[derive(Default)]
+pub struct FakeTranscriber {
+    // returned verbatim when set, otherwise a description of the input
+    pub text: Option<String>,
+}
+
+#[async_trait]
+impl Transcriber for FakeTranscriber {
+    async fn transcribe(&self, audio: &Path, _prompt: Option<&str>) -> anyhow::Result<Transcript> {
+        let text = match &self.text {
+            Some(text) => text.clone(),
+            None => format!(
+                "transcription of {} ({} bytes)",
+                audio.file_name().unwrap_or_default().to_string_lossy(),
+                tokio::fs::metadata(audio).await?.len()
+            ),
+        };
+        // untimed, the caller fills in a segment spanning the recording
+        Ok(Transcript {
+            text,
+            segments: vec![],
+        })
+    }
+}
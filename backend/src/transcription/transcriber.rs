@@ -0,0 +1,30 @@
+use std::{path::Path, sync::Arc};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+
+use crate::openai::client::OpenAIClient;
+
+use super::{fake::FakeTranscriber, transcript::Transcript, whisper_cpp::WhisperCppTranscriber};
+
+/// Speech-to-text backend used by the diary pipeline. The recording is passed
+/// as a file on disk whose extension names its format; `prompt` is text that
+/// preceded it, to keep spelling and style consistent across chunks. Segment
+/// times are relative to the start of the file.
+#[async_trait]
+pub trait Transcriber: Send + Sync {
+    async fn transcribe(&self, audio: &Path, prompt: Option<&str>) -> anyhow::Result<Transcript>;
+}
+
+/// Picks the backend named by `TRANSCRIBER` (`openai`, `whisper_cpp` or `fake`).
+pub fn transcriber_from_env(
+    openai_client: Arc<OpenAIClient>,
+) -> anyhow::Result<Arc<dyn Transcriber>> {
+    let backend = std::env::var("TRANSCRIBER").unwrap_or_else(|_| "openai".to_string());
+    match backend.as_str() {
+        "openai" => Ok(openai_client),
+        "whisper_cpp" => Ok(Arc::new(WhisperCppTranscriber::from_env()?)),
+        "fake" => Ok(Arc::new(FakeTranscriber::default())),
+        other => Err(anyhow!("Unknown TRANSCRIBER backend: {}", other)),
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/// A stretch of the recording and what was said in it, in milliseconds from
+/// the start of the recording.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TranscriptSegment {
+    pub start_ms: i32,
+    pub end_ms: i32,
+    pub text: String,
+    // none of the current backends tell speakers apart
+    pub speaker: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Transcript {
+    pub text: String,
+    // empty when neither the backend nor the recording's length gives timings
+    pub segments: Vec<TranscriptSegment>,
+}
+
+impl Transcript {
+    /// Joins segments transcribed separately, in order.
+    pub fn stitch(segments: Vec<TranscriptSegment>) -> Self {
+        let text = segments
+            .iter()
+            .map(|segment| segment.text.trim())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self { text, segments }
+    }
+}
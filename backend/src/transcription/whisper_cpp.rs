@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::process::Command;
+
+use super::{
+    transcriber::Transcriber,
+    transcript::{Transcript, TranscriptSegment},
+};
+
+// the `-oj` output of whisper-cli
+#[derive(Deserialize, Debug)]
+struct WhisperCppOutput {
+    transcription: Vec<WhisperCppSegment>,
+}
+
+#[derive(Deserialize, Debug)]
+struct WhisperCppSegment {
+    // milliseconds
+    offsets: WhisperCppOffsets,
+    text: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct WhisperCppOffsets {
+    from: i32,
+    to: i32,
+}
+
+/// Runs a local whisper.cpp build against a ggml model file. The upload is
+/// first converted to the 16kHz mono wav whisper.cpp expects using ffmpeg.
+pub struct WhisperCppTranscriber {
+    binary: String,
+    model: PathBuf,
+    ffmpeg: String,
+    language: String,
+    threads: Option<u32>,
+}
+
+impl WhisperCppTranscriber {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let model = std::env::var("WHISPER_CPP_MODEL")
+            .map_err(|_| anyhow!("WHISPER_CPP_MODEL must be set for the whisper_cpp backend"))?;
+        Ok(Self {
+            binary: std::env::var("WHISPER_CPP_BIN").unwrap_or_else(|_| "whisper-cli".to_string()),
+            model: PathBuf::from(model),
+            ffmpeg: std::env::var("FFMPEG_BIN").unwrap_or_else(|_| "ffmpeg".to_string()),
+            language: std::env::var("TRANSCRIBE_LANGUAGE").unwrap_or_else(|_| "ko".to_string()),
+            threads: std::env::var("WHISPER_CPP_THREADS")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+        })
+    }
+}
+
+#[async_trait]
+impl Transcriber for WhisperCppTranscriber {
+    async fn transcribe(&self, audio: &Path, prompt: Option<&str>) -> anyhow::Result<Transcript> {
+        let tmp_dir = tempfile::tempdir()?; // the directory will be dropped with the lifetime
+        let wav_path = tmp_dir.path().join("input.wav");
+        let output_base = tmp_dir.path().join("output");
+
+        let ffmpeg = Command::new(&self.ffmpeg)
+            .args(["-nostdin", "-loglevel", "error", "-y", "-i"])
+            .arg(audio)
+            .args(["-ar", "16000", "-ac", "1", "-c:a", "pcm_s16le"])
+            .arg(&wav_path)
+            .output()
+            .await
+            .with_context(|| format!("failed to run {}", self.ffmpeg))?;
+        if !ffmpeg.status.success() {
+            return Err(anyhow!(
+                "ffmpeg failed: {}",
+                String::from_utf8_lossy(&ffmpeg.stderr)
+            ));
+        }
+
+        let mut whisper = Command::new(&self.binary);
+        whisper
+            .arg("-m")
+            .arg(&self.model)
+            .arg("-f")
+            .arg(&wav_path)
+            .args(["-l", &self.language, "-np", "-oj", "-of"])
+            .arg(&output_base);
+        if let Some(threads) = self.threads {
+            whisper.args(["-t", &threads.to_string()]);
+        }
+        if let Some(prompt) = prompt {
+            whisper.args(["--prompt", prompt]);
+        }
+        let output = whisper
+            .output()
+            .await
+            .with_context(|| format!("failed to run {}", self.binary))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "whisper.cpp failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        let output = tokio::fs::read(output_base.with_extension("json")).await?;
+        let output: WhisperCppOutput = serde_json::from_slice(&output)?;
+        Ok(Transcript::stitch(
+            output
+                .transcription
+                .into_iter()
+                .map(|segment| TranscriptSegment {
+                    start_ms: segment.offsets.from,
+                    end_ms: segment.offsets.to,
+                    text: segment.text.trim().to_string(),
+                    speaker: None,
+                })
+                .collect(),
+        ))
+    }
+}
@@ -1,11 +1,15 @@
 use uuid::Uuid;
 
+use crate::audio::format::AudioFormat;
+
 pub mod coordinates;
-pub mod internal_error;
 pub mod parse_multipart;
+pub mod request_id;
+pub mod snippet;
 pub mod sqlx;
 
-pub fn get_diary_filename(user_id: Uuid, diary_id: i64) -> String {
-    // IOS recording only supports m4a
-    user_id.to_string() + "_" + &diary_id.to_string() + ".m4a"
+// named before the diary is saved; the extension follows the sniffed format
+// of the recording
+pub fn get_diary_filename(user_id: Uuid, upload_id: Uuid, format: AudioFormat) -> String {
+    format!("{}_{}.{}", user_id, upload_id, format.extension())
 }
@@ -1,4 +1,8 @@
-use axum::{body::Bytes, extract::Multipart};
+use axum::extract::Multipart;
+use tempfile::NamedTempFile;
+use tokio::io::AsyncWriteExt;
+
+use crate::error::{AppError, AppResult};
 
 pub struct MultipartMetadata {
     pub name: String,
@@ -6,10 +10,16 @@ pub struct MultipartMetadata {
     pub content_type: String,
 }
 
+/// Spools the first field of the upload into a temp file chunk by chunk, so
+/// large recordings never sit in memory. The file is removed when dropped.
 pub async fn parse_multipart(
     mut multipart: Multipart,
-) -> anyhow::Result<(Bytes, MultipartMetadata)> {
-    if let Some(field) = multipart.next_field().await? {
+) -> AppResult<(NamedTempFile, MultipartMetadata)> {
+    let field = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::Validation(e.body_text()))?;
+    if let Some(mut field) = field {
         // TODO: robust way of attaching names to files
         let name = field.name().unwrap_or("").to_string();
         let file_name = field.file_name().unwrap_or("").to_string();
@@ -19,10 +29,30 @@ pub async fn parse_multipart(
             file_name,
             content_type,
         };
-        let data = field.bytes().await?;
 
-        Ok((data, metadata))
+        let spooled = NamedTempFile::new().map_err(|e| AppError::Internal(e.into()))?;
+        let mut file =
+            tokio::fs::File::from_std(spooled.reopen().map_err(|e| AppError::Internal(e.into()))?);
+        let mut written = 0;
+        while let Some(chunk) = field
+            .chunk()
+            .await
+            .map_err(|e| AppError::Validation(e.body_text()))?
+        {
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| AppError::Internal(e.into()))?;
+            written += chunk.len();
+        }
+        file.flush()
+            .await
+            .map_err(|e| AppError::Internal(e.into()))?;
+        if written == 0 {
+            return Err(AppError::Validation("Uploaded file is empty".to_string()));
+        }
+
+        Ok((spooled, metadata))
     } else {
-        Err(anyhow::anyhow!("No file uploaded"))
+        Err(AppError::Validation("No file uploaded".to_string()))
     }
 }
@@ -0,0 +1,79 @@
+use serde::Serialize;
+
+// characters of context kept before the first match
+const LEAD_CHARS: usize = 40;
+const SNIPPET_CHARS: usize = 160;
+
+/// An excerpt around the first match of a search. `highlights` are
+/// `[start, end)` offsets into `text`, counted in Unicode scalar values.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Snippet {
+    pub text: String,
+    pub highlights: Vec<[usize; 2]>,
+}
+
+/// Cuts an excerpt of `text` around the earliest case-insensitive occurrence
+/// of any of `terms`, marking every occurrence inside it. `None` if no term
+/// occurs.
+pub fn snippet(text: &str, terms: &[String]) -> Option<Snippet> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let lowered = chars.iter().map(|c| lower(*c)).collect::<Vec<_>>();
+
+    let mut matches = Vec::new();
+    for term in terms {
+        let term = term.chars().map(lower).collect::<Vec<_>>();
+        if term.is_empty() || term.len() > lowered.len() {
+            continue;
+        }
+        matches.extend(
+            lowered
+                .windows(term.len())
+                .enumerate()
+                .filter(|(_, window)| *window == term.as_slice())
+                .map(|(i, _)| (i, i + term.len())),
+        );
+    }
+    matches.sort_unstable();
+    let first = matches.first()?.0;
+
+    let start = first.saturating_sub(LEAD_CHARS);
+    let end = (start + SNIPPET_CHARS).min(chars.len());
+    let mut excerpt = String::new();
+    if start > 0 {
+        excerpt.push('…');
+    }
+    excerpt.extend(&chars[start..end]);
+    if end < chars.len() {
+        excerpt.push('…');
+    }
+
+    // offsets into the excerpt, overlapping matches merged
+    let shift = usize::from(start > 0);
+    let mut highlights: Vec<[usize; 2]> = Vec::new();
+    for (match_start, match_end) in matches {
+        if match_start >= end {
+            break;
+        }
+        let (match_start, match_end) = (
+            match_start - start + shift,
+            match_end.min(end) - start + shift,
+        );
+        match highlights.last_mut() {
+            Some(last) if match_start <= last[1] => last[1] = last[1].max(match_end),
+            _ => highlights.push([match_start, match_end]),
+        }
+    }
+    Some(Snippet {
+        text: excerpt,
+        highlights,
+    })
+}
+
+// one-to-one so offsets line up with the original text
+fn lower(c: char) -> char {
+    let mut lowered = c.to_lowercase();
+    match (lowered.next(), lowered.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
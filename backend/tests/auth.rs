@@ -0,0 +1,158 @@
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, Request, StatusCode},
+    response::IntoResponse,
+};
+use jsonwebtoken::{encode, jwk::JwkSet, Algorithm, EncodingKey, Header};
+use recordiary::auth::{jwt::JwtVerifier, user::AuthUser};
+use serde_json::json;
+use uuid::Uuid;
+
+const SECRET: &[u8] = b"local-test-secret";
+const AUDIENCE: &str = "authenticated";
+const KID: &str = "test-key";
+const RSA_KEY: &[u8] = include_bytes!("fixtures/jwt_rs256.pem");
+const JWKS: &str = include_str!("fixtures/jwks.json");
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn claims(sub: Uuid, exp: u64, role: &str) -> serde_json::Value {
+    json!({ "sub": sub, "exp": exp, "aud": AUDIENCE, "role": role })
+}
+
+fn hs256(claims: &serde_json::Value, secret: &[u8]) -> String {
+    encode(
+        &Header::new(Algorithm::HS256),
+        claims,
+        &EncodingKey::from_secret(secret),
+    )
+    .unwrap()
+}
+
+fn rs256(claims: &serde_json::Value, kid: &str) -> String {
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(kid.to_string());
+    encode(
+        &header,
+        claims,
+        &EncodingKey::from_rsa_pem(RSA_KEY).unwrap(),
+    )
+    .unwrap()
+}
+
+fn secret_verifier() -> Arc<JwtVerifier> {
+    Arc::new(JwtVerifier::from_secret(SECRET, AUDIENCE.to_string()))
+}
+
+fn jwks_verifier() -> Arc<JwtVerifier> {
+    let jwks: JwkSet = serde_json::from_str(JWKS).unwrap();
+    Arc::new(JwtVerifier::from_jwks(&jwks, AUDIENCE.to_string()).unwrap())
+}
+
+/// Runs the extractor on a request with the given `Authorization` header.
+async fn extract(
+    verifier: Arc<JwtVerifier>,
+    authorization: Option<String>,
+) -> Result<Uuid, StatusCode> {
+    let mut request = Request::builder();
+    if let Some(authorization) = authorization {
+        request = request.header(AUTHORIZATION, authorization);
+    }
+    let (mut parts, _) = request.body(()).unwrap().into_parts();
+    AuthUser::from_request_parts(&mut parts, &verifier)
+        .await
+        .map(|AuthUser(user_id)| user_id)
+        .map_err(|e| e.into_response().status())
+}
+
+fn bearer(token: String) -> Option<String> {
+    Some(format!("Bearer {}", token))
+}
+
+#[tokio::test]
+async fn accepts_a_valid_hs256_token() {
+    let user_id = Uuid::new_v4();
+    let token = hs256(&claims(user_id, now() + 60, AUDIENCE), SECRET);
+    assert_eq!(extract(secret_verifier(), bearer(token)).await, Ok(user_id));
+}
+
+#[tokio::test]
+async fn accepts_a_valid_rs256_token() {
+    let user_id = Uuid::new_v4();
+    let token = rs256(&claims(user_id, now() + 60, AUDIENCE), KID);
+    assert_eq!(extract(jwks_verifier(), bearer(token)).await, Ok(user_id));
+}
+
+#[tokio::test]
+async fn rejects_a_missing_token() {
+    assert_eq!(
+        extract(secret_verifier(), None).await,
+        Err(StatusCode::UNAUTHORIZED)
+    );
+    // not a bearer token
+    assert_eq!(
+        extract(secret_verifier(), Some("Basic dXNlcjpwYXNz".to_string())).await,
+        Err(StatusCode::UNAUTHORIZED)
+    );
+}
+
+#[tokio::test]
+async fn rejects_a_forged_token() {
+    let claims = claims(Uuid::new_v4(), now() + 60, AUDIENCE);
+    assert_eq!(
+        extract(secret_verifier(), bearer(hs256(&claims, b"someone-else"))).await,
+        Err(StatusCode::UNAUTHORIZED)
+    );
+    // signed with the shared secret instead of the private key
+    assert_eq!(
+        extract(jwks_verifier(), bearer(hs256(&claims, SECRET))).await,
+        Err(StatusCode::UNAUTHORIZED)
+    );
+    assert_eq!(
+        extract(jwks_verifier(), bearer(rs256(&claims, "unknown-key"))).await,
+        Err(StatusCode::UNAUTHORIZED)
+    );
+    assert_eq!(
+        extract(secret_verifier(), bearer("not.a.jwt".to_string())).await,
+        Err(StatusCode::UNAUTHORIZED)
+    );
+}
+
+#[tokio::test]
+async fn rejects_an_expired_token() {
+    // well past the default leeway
+    let claims = claims(Uuid::new_v4(), now() - 600, AUDIENCE);
+    assert_eq!(
+        extract(secret_verifier(), bearer(hs256(&claims, SECRET))).await,
+        Err(StatusCode::UNAUTHORIZED)
+    );
+    assert_eq!(
+        extract(jwks_verifier(), bearer(rs256(&claims, KID))).await,
+        Err(StatusCode::UNAUTHORIZED)
+    );
+}
+
+#[tokio::test]
+async fn forbids_non_user_roles() {
+    for role in ["anon", "service_role"] {
+        let claims = claims(Uuid::new_v4(), now() + 60, role);
+        assert_eq!(
+            extract(secret_verifier(), bearer(hs256(&claims, SECRET))).await,
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            extract(jwks_verifier(), bearer(rs256(&claims, KID))).await,
+            Err(StatusCode::FORBIDDEN)
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use recordiary::{
+    db::job::{claim_job, enqueue_job},
+    jobs::{kind::Job, worker::hold_lease},
+};
+use sqlx::PgPool;
+
+const LEASE: Duration = Duration::from_secs(1);
+
+async fn enqueue(pool: &PgPool) -> i64 {
+    let mut conn = pool.acquire().await.unwrap();
+    enqueue_job(&mut conn, Job::EmbedDiary { diary_id: 1 })
+        .await
+        .unwrap()
+}
+
+// a worker that claims the job and then never finishes it, renewing its lease
+// like a real one until it is killed
+fn spawn_stuck_worker(pool: PgPool) -> tokio::task::JoinHandle<i64> {
+    tokio::spawn(async move {
+        let job = claim_job(&pool, "worker-a", LEASE.as_secs_f64())
+            .await
+            .unwrap()
+            .expect("a queued job");
+        hold_lease(&pool, job.id, "worker-a", LEASE).await;
+        job.id
+    })
+}
+
+#[sqlx::test]
+async fn killed_worker_job_is_reclaimed(pool: PgPool) {
+    let job_id = enqueue(&pool).await;
+    let worker = spawn_stuck_worker(pool.clone());
+    tokio::time::sleep(LEASE * 2).await;
+
+    // renewed while the worker is alive
+    let claimed = claim_job(&pool, "worker-b", LEASE.as_secs_f64())
+        .await
+        .unwrap();
+    assert!(claimed.is_none(), "a live worker's job was reclaimed");
+
+    worker.abort();
+    tokio::time::sleep(LEASE + Duration::from_millis(500)).await;
+
+    let reclaimed = claim_job(&pool, "worker-b", LEASE.as_secs_f64())
+        .await
+        .unwrap()
+        .expect("the dead worker's job is reclaimed");
+    assert_eq!(reclaimed.id, job_id);
+    assert_eq!(reclaimed.attempts, 2);
+    assert_eq!(reclaimed.locked_by.as_deref(), Some("worker-b"));
+}
+
+#[sqlx::test]
+async fn reclaimed_job_stops_the_old_lease(pool: PgPool) {
+    let job_id = enqueue(&pool).await;
+    let job = claim_job(&pool, "worker-a", LEASE.as_secs_f64())
+        .await
+        .unwrap()
+        .unwrap();
+    tokio::time::sleep(LEASE + Duration::from_millis(500)).await;
+    let reclaimed = claim_job(&pool, "worker-b", LEASE.as_secs_f64())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reclaimed.id, job_id);
+
+    // the first worker finds out at its next renewal
+    tokio::time::timeout(LEASE, hold_lease(&pool, job.id, "worker-a", LEASE))
+        .await
+        .expect("hold_lease returns once the job was reclaimed");
+}
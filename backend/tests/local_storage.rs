@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use recordiary::storage::{
+    local::LocalObjectStore,
+    store::{Bucket, ObjectStore},
+};
+use reqwest::Url;
+
+const KEY: &str = "11111111-1111-1111-1111-111111111111_1.m4a";
+
+fn store(root: &std::path::Path) -> LocalObjectStore {
+    LocalObjectStore::new(
+        root.to_path_buf(),
+        Url::parse("http://localhost:3000").unwrap(),
+        b"local-signing-key".to_vec(),
+    )
+}
+
+/// `(bucket, key, expires, signature)` of a signed URL.
+fn parse(url: &str) -> (Bucket, String, u64, String) {
+    let url = Url::parse(url).unwrap();
+    let mut segments = url.path_segments().unwrap();
+    assert_eq!(segments.next(), Some("storage"));
+    let bucket = Bucket::from_name(segments.next().unwrap()).unwrap();
+    let key = segments.collect::<Vec<_>>().join("/");
+    let query = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .unwrap()
+    };
+    (
+        bucket,
+        key,
+        query("expires").parse().unwrap(),
+        query("signature"),
+    )
+}
+
+#[tokio::test]
+async fn signed_urls_verify() {
+    let root = tempfile::tempdir().unwrap();
+    let store = store(root.path());
+    let url = store
+        .sign(Bucket::Audio, KEY, Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert!(url.starts_with("http://localhost:3000/storage/audio/"));
+
+    let (bucket, key, expires, signature) = parse(&url);
+    assert_eq!(key, KEY);
+    assert!(store.verify(bucket, &key, expires, &signature));
+}
+
+#[tokio::test]
+async fn tampered_urls_do_not_verify() {
+    let root = tempfile::tempdir().unwrap();
+    let store = store(root.path());
+    let url = store
+        .sign(Bucket::Audio, KEY, Duration::from_secs(60))
+        .await
+        .unwrap();
+    let (bucket, key, expires, signature) = parse(&url);
+
+    assert!(!store.verify(Bucket::Model, &key, expires, &signature));
+    assert!(!store.verify(bucket, "someone-else.m4a", expires, &signature));
+    assert!(!store.verify(bucket, &key, expires + 3600, &signature));
+    assert!(!store.verify(bucket, &key, expires, &"0".repeat(signature.len())));
+    assert!(!store.verify(bucket, &key, expires, "not hex"));
+
+    // signed with another key, e.g. before a restart without STORAGE_SIGNING_KEY
+    let other = LocalObjectStore::new(
+        root.path().to_path_buf(),
+        Url::parse("http://localhost:3000").unwrap(),
+        b"another-key".to_vec(),
+    );
+    assert!(!other.verify(bucket, &key, expires, &signature));
+}
+
+#[tokio::test]
+async fn expired_urls_do_not_verify() {
+    let root = tempfile::tempdir().unwrap();
+    let store = store(root.path());
+    let url = store
+        .sign(Bucket::Audio, KEY, Duration::ZERO)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let (bucket, key, expires, signature) = parse(&url);
+    assert!(!store.verify(bucket, &key, expires, &signature));
+}
+
+#[tokio::test]
+async fn objects_round_trip_on_disk() {
+    let root = tempfile::tempdir().unwrap();
+    let store = store(root.path());
+    store
+        .put(Bucket::Audio, KEY, b"audio".to_vec(), "audio/mp4")
+        .await
+        .unwrap();
+    assert_eq!(store.get(Bucket::Audio, KEY).await.unwrap(), b"audio");
+    assert_eq!(
+        store.list(Bucket::Audio, "11111111").await.unwrap(),
+        vec![KEY.to_string()]
+    );
+    assert!(store.list(Bucket::Model, "").await.unwrap().is_empty());
+
+    store.delete(Bucket::Audio, KEY).await.unwrap();
+    assert!(store.get(Bucket::Audio, KEY).await.is_err());
+    // deleting twice is fine
+    store.delete(Bucket::Audio, KEY).await.unwrap();
+}
+
+#[tokio::test]
+async fn keys_cannot_escape_the_bucket() {
+    let root = tempfile::tempdir().unwrap();
+    let store = store(root.path());
+    for key in ["../model/x.glb", "/etc/passwd", "a/../../b", ""] {
+        assert!(store.path(Bucket::Audio, key).is_err(), "{}", key);
+        assert!(store
+            .sign(Bucket::Audio, key, Duration::from_secs(60))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use recordiary::{
+    analysis::{
+        emotion::{Emotion, EmotionAnalysis, EmotionScores},
+        mock::ScriptedAnalyzer,
+    },
+    db::{
+        diary_embedding::get_diary_embedding,
+        job::{claim_job, complete_job, JobRow},
+    },
+    embedding::{embedder::Embedder, hash::HashEmbedder},
+    jobs::kind::Job,
+    openai::diary::{embed_diary, summarize_diary, transcribe_diary},
+    storage::{
+        local::LocalObjectStore,
+        store::{Bucket, ObjectStore},
+    },
+    transcription::{
+        chunked::{ChunkConfig, ChunkedTranscriber},
+        fake::FakeTranscriber,
+    },
+};
+use reqwest::Url;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const TRANSCRIPTION: &str = "오늘은 친구와 학교에 가서 하루 종일 웃었다";
+const SUMMARY: &str = "친구와 학교";
+const AUDIO_KEY: &str = "diary.m4a";
+const DURATION_MS: i32 = 4_000;
+
+fn transcriber() -> ChunkedTranscriber {
+    ChunkedTranscriber::new(
+        Arc::new(FakeTranscriber {
+            text: Some(TRANSCRIPTION.to_string()),
+        }),
+        ChunkConfig {
+            max_chunk_ms: 600_000,
+            concurrency: 1,
+            silence_db: -35.0,
+            min_silence_secs: 0.5,
+            // short recordings are never cut, so neither is run
+            ffmpeg: "ffmpeg".to_string(),
+            ffprobe: "ffprobe".to_string(),
+        },
+    )
+}
+
+fn analyzer() -> ScriptedAnalyzer {
+    ScriptedAnalyzer::new(
+        vec![SUMMARY.to_string()],
+        vec![EmotionAnalysis {
+            emotion: Emotion::Happiness,
+            scores: EmotionScores::from([(Emotion::Happiness, 0.9), (Emotion::Calm, 0.1)]),
+        }],
+    )
+}
+
+async fn status(pool: &PgPool, diary_id: i64) -> String {
+    sqlx::query_scalar("SELECT processing_status::text FROM diary WHERE id = $1")
+        .bind(diary_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Claims the next job, which must be of `kind`, and marks it done.
+async fn next_job(pool: &PgPool, kind: &str) -> JobRow {
+    let job = claim_job(pool, "test-worker", 600.0)
+        .await
+        .unwrap()
+        .unwrap_or_else(|| panic!("a queued {} job", kind));
+    assert_eq!(job.kind, kind);
+    complete_job(pool, job.id, "test-worker").await.unwrap();
+    job
+}
+
+#[sqlx::test]
+async fn upload_is_transcribed_summarized_and_embedded(pool: PgPool) {
+    let storage = tempfile::tempdir().unwrap();
+    let object_store = LocalObjectStore::new(
+        storage.path().to_path_buf(),
+        Url::parse("http://localhost:3000").unwrap(),
+        b"local-signing-key".to_vec(),
+    );
+    object_store
+        .put(Bucket::Audio, AUDIO_KEY, b"audio".to_vec(), "audio/mp4")
+        .await
+        .unwrap();
+    let diary_id: i64 = sqlx::query_scalar(
+        "INSERT INTO diary (user_id, audio_key, duration_ms) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(Uuid::new_v4())
+    .bind(AUDIO_KEY)
+    .bind(DURATION_MS)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(status(&pool, diary_id).await, "uploaded");
+
+    transcribe_diary(&pool, &object_store, &transcriber(), diary_id)
+        .await
+        .unwrap();
+    assert_eq!(status(&pool, diary_id).await, "summarizing");
+    let (transcription,): (Option<String>,) =
+        sqlx::query_as("SELECT transcription FROM diary WHERE id = $1")
+            .bind(diary_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(transcription.as_deref(), Some(TRANSCRIPTION));
+    // the fake gives no timings, so one segment spans the recording
+    let segments: Vec<(i32, i32, String)> = sqlx::query_as(
+        "SELECT start_ms, end_ms, text FROM diary_segment WHERE diary_id = $1 ORDER BY idx",
+    )
+    .bind(diary_id)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(segments, vec![(0, DURATION_MS, TRANSCRIPTION.to_string())]);
+
+    let job = next_job(&pool, "summarize_diary").await;
+    assert!(matches!(job.payload.0, Job::SummarizeDiary { diary_id: id } if id == diary_id));
+    summarize_diary(&pool, &analyzer(), diary_id).await.unwrap();
+    assert_eq!(status(&pool, diary_id).await, "complete");
+    let (summary, emotion): (Option<String>, Option<String>) =
+        sqlx::query_as("SELECT summary, emotion::text FROM diary WHERE id = $1")
+            .bind(diary_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(summary.as_deref(), Some(SUMMARY));
+    assert_eq!(emotion.as_deref(), Some("happiness"));
+
+    let job = next_job(&pool, "embed_diary").await;
+    assert!(matches!(job.payload.0, Job::EmbedDiary { diary_id: id } if id == diary_id));
+    let embedder = HashEmbedder::default();
+    embed_diary(&pool, &embedder, diary_id).await.unwrap();
+    let embedding = get_diary_embedding(&pool, diary_id)
+        .await
+        .unwrap()
+        .expect("the diary is indexed");
+    assert_eq!(embedding.model, embedder.model());
+    // embedded from the summary and the transcription
+    let expected = embedder
+        .embed(&format!("{}\n\n{}", SUMMARY, TRANSCRIPTION))
+        .await
+        .unwrap();
+    assert_eq!(embedding.embedding, expected);
+
+    assert!(claim_job(&pool, "test-worker", 600.0)
+        .await
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,104 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    body::to_bytes,
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use hyper::StatusCode;
+use recordiary::{
+    auth::user::AuthUser,
+    handlers::share::{create_share, get_shared_room},
+    room::grid::RoomGrid,
+    storage::{links::LinkSigner, local::LocalObjectStore},
+};
+use reqwest::Url;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const GRID: RoomGrid = RoomGrid {
+    width: 10,
+    depth: 10,
+    height: 10,
+};
+
+/// Places `deco` in the room of 2024-12 through a new diary of `user_id`.
+async fn place(pool: &PgPool, user_id: Uuid, deco: &str, is_private: bool, x: i64) {
+    let diary_id: i64 = sqlx::query_scalar(
+        "INSERT INTO diary (user_id, local_date, is_private) VALUES ($1, '2024-12-05', $2) RETURNING id",
+    )
+    .bind(user_id)
+    .bind(is_private)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    let deco_id: i64 = sqlx::query_scalar(
+        "INSERT INTO deco (name, asset_link, asset_key) VALUES ($1, '', $2) RETURNING id",
+    )
+    .bind(deco)
+    .bind(format!("{}.glb", deco))
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO user_deco (user_id, diary_id, deco_id, is_placed, coordinates) VALUES ($1, $2, $3, true, $4)",
+    )
+    .bind(user_id)
+    .bind(diary_id)
+    .bind(deco_id)
+    .bind(json!({ "x": x, "y": 0, "z": 0, "orientation": 0 }))
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+#[sqlx::test]
+async fn shared_room_leaves_out_private_diaries(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+    place(&pool, user_id, "lamp", false, 0).await;
+    place(&pool, user_id, "secret_box", true, 5).await;
+
+    let Json(share) = create_share(
+        State(pool.clone()),
+        AuthUser(user_id),
+        Json(serde_json::from_value(json!({ "year": 2024, "month": 12 })).unwrap()),
+    )
+    .await
+    .unwrap();
+    let share = serde_json::to_value(share).unwrap();
+    let token = share["token"].as_str().unwrap().to_string();
+
+    let storage = tempfile::tempdir().unwrap();
+    let link_signer = LinkSigner::new(
+        Arc::new(LocalObjectStore::new(
+            storage.path().to_path_buf(),
+            Url::parse("http://localhost:3000").unwrap(),
+            b"local-signing-key".to_vec(),
+        )),
+        Duration::from_secs(600),
+    );
+    let response = get_shared_room(
+        State(pool),
+        State(Arc::new(link_signer)),
+        State(GRID),
+        Path(token),
+    )
+    .await
+    .unwrap()
+    .into_response();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    assert!(!String::from_utf8_lossy(&body).contains("secret_box"));
+    let room: Value = serde_json::from_slice(&body).unwrap();
+    let decos = room["decos"].as_array().unwrap();
+    assert_eq!(decos.len(), 1);
+    assert_eq!(decos[0]["name"], "lamp");
+    assert_eq!(decos[0]["local_date"], "2024-12-05");
+    assert!(decos[0]["asset_link"]
+        .as_str()
+        .unwrap()
+        .contains("lamp.glb"));
+}